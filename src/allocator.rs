@@ -0,0 +1,85 @@
+use crate::{AllocOptions, DefaultImpl, OsImpl, Slab};
+use std::alloc::{GlobalAlloc, Layout};
+use std::ptr::{self, NonNull};
+
+/// Allocator keeping everything it hands out in non-swappable
+/// memory
+///
+/// Small blocks are packed into shared pages by a [Slab], larger
+/// ones get locked pages of their own. Freed blocks are wiped.
+///
+/// It can serve as the process-wide allocator:
+///
+/// ```no_run
+/// use unswap::UnswapAlloc;
+///
+/// #[global_allocator]
+/// static ALLOC: UnswapAlloc = UnswapAlloc::new();
+/// ```
+///
+/// Note that all the heap memory then counts towards
+/// `RLIMIT_MEMLOCK`, and an allocation failure aborts the
+/// process.
+///
+/// With the `allocator-api` feature on a nightly compiler, it
+/// also implements `Allocator`, so it can be given to a single
+/// collection, e.g. `Vec::new_in(&ALLOC)`.
+pub struct UnswapAlloc<A: OsImpl = DefaultImpl> {
+    slab: Slab<A>,
+}
+
+impl<A: OsImpl> UnswapAlloc<A> {
+    /// Creates an allocator, which allocates pages from the `A`
+    /// backend with the given `options`
+    pub const fn new_with(options: AllocOptions) -> Self {
+        Self {
+            slab: Slab::new(options),
+        }
+    }
+
+    /// Returns the options the pages are allocated with
+    pub fn options(&self) -> &AllocOptions {
+        self.slab.options()
+    }
+}
+
+impl UnswapAlloc {
+    /// Creates an allocator with the default options
+    pub const fn new() -> Self {
+        Self::new_with(AllocOptions::new())
+    }
+}
+
+impl Default for UnswapAlloc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<A: OsImpl> GlobalAlloc for UnswapAlloc<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match self.slab.alloc(layout) {
+            Ok(at) => at.as_ptr(),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, at: *mut u8, layout: Layout) {
+        self.slab.free(NonNull::new_unchecked(at), layout)
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+unsafe impl<A: OsImpl> std::alloc::Allocator for UnswapAlloc<A> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, std::alloc::AllocError> {
+        let at = self
+            .slab
+            .alloc(layout)
+            .map_err(|_| std::alloc::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(at, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, at: NonNull<u8>, layout: Layout) {
+        self.slab.free(at, layout)
+    }
+}
@@ -0,0 +1,222 @@
+use crate::raw::{wipe_bytes, RawPages};
+use crate::{AllocOptions, DefaultImpl, Error, OsImpl, Zeroable};
+use std::alloc::Layout;
+use std::cell::Cell;
+use std::mem::MaybeUninit;
+use std::ptr::{self, NonNull};
+use std::slice;
+use std::str;
+
+/// Bump allocator over a single locked region reserved up front
+///
+/// Values allocated from the arena borrow it, and are all wiped
+/// at once when the arena is [reset](UnswapArena::reset) or
+/// dropped. Destructors are never run, so only `Copy` values can
+/// be allocated, which cannot own memory outside of the arena.
+///
+/// ```
+/// use unswap::UnswapArena;
+///
+/// let mut arena = UnswapArena::with_capacity(4096);
+/// let key = arena.try_alloc_slice_copy(&[1u8; 32]).unwrap();
+/// let nonce = arena.try_alloc_zeroed::<[u8; 12]>().unwrap();
+/// key[0] ^= nonce[0];
+///
+/// arena.reset();
+/// assert_eq!(arena.used(), 0);
+/// ```
+pub struct UnswapArena<A: OsImpl = DefaultImpl> {
+    pages: RawPages<A>,
+    used: Cell<usize>,
+}
+
+// Every allocation gets a distinct part of the region, so handing
+// out mutable references from a shared one is sound
+#[allow(clippy::mut_from_ref)]
+impl<A: OsImpl> UnswapArena<A> {
+    /// Same as [UnswapArena::try_with_capacity], but allocates the
+    /// pages from the `A` backend with the given `options`.
+    pub fn try_with_capacity_with(options: AllocOptions, capacity: usize) -> Result<Self, Error> {
+        Ok(Self {
+            pages: RawPages::try_alloc_array::<u8>(options, capacity)?,
+            used: Cell::new(0),
+        })
+    }
+
+    /// Returns the size of the region in bytes
+    pub fn capacity(&self) -> usize {
+        self.pages.capacity::<u8>()
+    }
+
+    /// Returns the number of bytes handed out since the last reset,
+    /// including alignment padding
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// Returns the options the arena pages were allocated with
+    pub fn options(&self) -> &AllocOptions {
+        self.pages.options()
+    }
+
+    /// Wipes everything allocated from the arena, making the whole
+    /// region available again
+    pub fn reset(&mut self) {
+        unsafe {
+            wipe_bytes(self.pages.as_ptr() as *mut u8, self.used.get());
+        }
+        self.used.set(0);
+    }
+
+    /// Carves out a block fitting `layout`
+    fn alloc_layout(&self, layout: Layout) -> Result<NonNull<u8>, Error> {
+        if layout.size() == 0 {
+            // Zero-sized values take no room at all
+            return Ok(unsafe { NonNull::new_unchecked(layout.align() as *mut u8) });
+        }
+        let base = self.pages.as_ptr() as usize;
+        let start = (base + self.used.get())
+            .checked_add(layout.align() - 1)
+            .ok_or(Error::ArenaFull)?
+            & !(layout.align() - 1);
+        let end = start.checked_add(layout.size()).ok_or(Error::ArenaFull)?;
+        if end > base + self.capacity() {
+            return Err(Error::ArenaFull);
+        }
+        self.used.set(end - base);
+
+        Ok(NonNull::new(start as *mut u8).unwrap())
+    }
+
+    /// Moves `value` into the arena
+    pub fn try_alloc<T: Copy>(&self, value: T) -> Result<&mut T, Error> {
+        let at = self.alloc_layout(Layout::new::<T>())?.cast::<T>();
+        unsafe {
+            at.as_ptr().write(value);
+            Ok(&mut *at.as_ptr())
+        }
+    }
+
+    /// Moves `value` into the arena.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena has no room left.
+    pub fn alloc<T: Copy>(&self, value: T) -> &mut T {
+        self.try_alloc(value).expect("Arena has no room left")
+    }
+
+    /// Allocates a zero-initialized value
+    pub fn try_alloc_zeroed<T: Zeroable>(&self) -> Result<&mut T, Error> {
+        let at = self.alloc_layout(Layout::new::<T>())?.cast::<T>();
+        unsafe {
+            ptr::write_bytes(at.as_ptr(), 0, 1);
+            Ok(&mut *at.as_ptr())
+        }
+    }
+
+    /// Allocates a zero-initialized value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena has no room left.
+    pub fn alloc_zeroed<T: Zeroable>(&self) -> &mut T {
+        self.try_alloc_zeroed().expect("Arena has no room left")
+    }
+
+    /// Allocates a slice of `len` elements, initializing each one
+    /// with the value returned by `f` for its index
+    pub fn try_alloc_slice_from_fn<T: Copy, F: FnMut(usize) -> T>(
+        &self,
+        len: usize,
+        mut f: F,
+    ) -> Result<&mut [T], Error> {
+        let layout = Layout::array::<T>(len).map_err(|_| Error::LayoutError)?;
+        let at = self.alloc_layout(layout)?.cast::<MaybeUninit<T>>();
+        let slots = unsafe { slice::from_raw_parts_mut(at.as_ptr(), len) };
+        for (i, slot) in slots.iter_mut().enumerate() {
+            slot.write(f(i));
+        }
+
+        Ok(unsafe { slice::from_raw_parts_mut(at.as_ptr() as *mut T, len) })
+    }
+
+    /// Allocates a slice of `len` elements, initializing each one
+    /// with the value returned by `f` for its index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena has no room left.
+    pub fn alloc_slice_from_fn<T: Copy, F: FnMut(usize) -> T>(&self, len: usize, f: F) -> &mut [T] {
+        self.try_alloc_slice_from_fn(len, f)
+            .expect("Arena has no room left")
+    }
+
+    /// Allocates a slice of `len` zero-initialized elements
+    pub fn try_alloc_slice_zeroed<T: Zeroable>(&self, len: usize) -> Result<&mut [T], Error> {
+        let layout = Layout::array::<T>(len).map_err(|_| Error::LayoutError)?;
+        let at = self.alloc_layout(layout)?.cast::<T>();
+        unsafe {
+            ptr::write_bytes(at.as_ptr(), 0, len);
+            Ok(slice::from_raw_parts_mut(at.as_ptr(), len))
+        }
+    }
+
+    /// Allocates a slice of `len` zero-initialized elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena has no room left.
+    pub fn alloc_slice_zeroed<T: Zeroable>(&self, len: usize) -> &mut [T] {
+        self.try_alloc_slice_zeroed(len)
+            .expect("Arena has no room left")
+    }
+
+    /// Copies `values` into the arena
+    pub fn try_alloc_slice_copy<T: Copy>(&self, values: &[T]) -> Result<&mut [T], Error> {
+        self.try_alloc_slice_from_fn(values.len(), |i| values[i])
+    }
+
+    /// Copies `values` into the arena.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena has no room left.
+    pub fn alloc_slice_copy<T: Copy>(&self, values: &[T]) -> &mut [T] {
+        self.try_alloc_slice_copy(values)
+            .expect("Arena has no room left")
+    }
+
+    /// Copies `text` into the arena
+    pub fn try_alloc_str(&self, text: &str) -> Result<&mut str, Error> {
+        let bytes = self.try_alloc_slice_copy(text.as_bytes())?;
+        Ok(unsafe { str::from_utf8_unchecked_mut(bytes) })
+    }
+
+    /// Copies `text` into the arena.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena has no room left.
+    pub fn alloc_str(&self, text: &str) -> &mut str {
+        self.try_alloc_str(text).expect("Arena has no room left")
+    }
+}
+
+impl UnswapArena {
+    /// Reserves a locked region of at least `capacity` bytes for
+    /// the arena
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, Error> {
+        Self::try_with_capacity_with(AllocOptions::new(), capacity)
+    }
+
+    /// Reserves a locked region of at least `capacity` bytes for
+    /// the arena.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::try_with_capacity(capacity).expect("Failed to allocate locked memory pages")
+    }
+}
@@ -0,0 +1,221 @@
+use crate::raw::RawPages;
+use crate::{AllocOptions, DefaultImpl, Error, OsImpl, UnswapArray, Zeroable};
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+
+/// Single value residing in non-swappable memory
+///
+/// Unlike [UnswapArray], the value does not need to be `Clone`,
+/// and may be unsized, e.g. `UnswapBox<[u8]>` or `UnswapBox<str>`.
+pub struct UnswapBox<T: ?Sized, A: OsImpl = DefaultImpl> {
+    pages: RawPages<A>,
+    value: NonNull<T>,
+}
+
+// The box owns the value, so it can be sent and shared whenever
+// the value itself can
+unsafe impl<T: ?Sized + Send, A: OsImpl> Send for UnswapBox<T, A> {}
+unsafe impl<T: ?Sized + Sync, A: OsImpl> Sync for UnswapBox<T, A> {}
+
+impl<T, A: OsImpl> UnswapBox<MaybeUninit<T>, A> {
+    /// Converts to `UnswapBox<T>` once the value is initialized.
+    ///
+    /// # Safety
+    ///
+    /// The value must have been fully initialized.
+    pub unsafe fn assume_init(self) -> UnswapBox<T, A> {
+        let this = ManuallyDrop::new(self);
+        UnswapBox {
+            pages: ptr::read(&this.pages),
+            value: this.value.cast(),
+        }
+    }
+}
+
+impl<T, A: OsImpl> UnswapBox<T, A> {
+    /// Same as [UnswapBox::try_new_uninit], but allocates the pages
+    /// from the `A` backend with the given `options`.
+    pub fn try_new_uninit_with(
+        options: AllocOptions,
+    ) -> Result<UnswapBox<MaybeUninit<T>, A>, Error> {
+        let pages = RawPages::try_alloc_array::<T>(options, 1)?;
+        let value = NonNull::new(pages.as_ptr() as *mut MaybeUninit<T>).unwrap();
+
+        Ok(UnswapBox { pages, value })
+    }
+
+    /// Same as [UnswapBox::try_new], but allocates the pages from
+    /// the `A` backend with the given `options`.
+    pub fn try_new_with(options: AllocOptions, value: T) -> Result<Self, Error> {
+        let mut uninit = Self::try_new_uninit_with(options)?;
+        uninit.write(value);
+        Ok(unsafe { uninit.assume_init() })
+    }
+}
+
+impl<T: Zeroable, A: OsImpl> UnswapBox<T, A> {
+    /// Same as [UnswapBox::try_init], but allocates the pages from
+    /// the `A` backend with the given `options`.
+    pub fn try_init_with<F: FnOnce(&mut T)>(options: AllocOptions, f: F) -> Result<Self, Error> {
+        let mut uninit = Self::try_new_uninit_with(options)?;
+        unsafe {
+            ptr::write_bytes(uninit.as_mut_ptr(), 0, 1);
+        }
+        let mut this = unsafe { uninit.assume_init() };
+        f(&mut this);
+
+        Ok(this)
+    }
+}
+
+impl<T> UnswapBox<T> {
+    /// Moves `value` into locked memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked,
+    /// see [UnswapBox::try_new] for a non-panicking version.
+    pub fn new(value: T) -> Self {
+        Self::try_new(value).expect("Failed to allocate locked memory pages")
+    }
+
+    /// Moves `value` into locked memory.
+    ///
+    /// Note that `value` itself may leave copies on the stack,
+    /// use [UnswapBox::try_init] or [UnswapBox::try_new_uninit]
+    /// to construct it in place.
+    pub fn try_new(value: T) -> Result<Self, Error> {
+        Self::try_new_with(AllocOptions::new(), value)
+    }
+
+    /// Allocates room for a value, which can be initialized in
+    /// place afterwards
+    pub fn try_new_uninit() -> Result<UnswapBox<MaybeUninit<T>>, Error> {
+        Self::try_new_uninit_with(AllocOptions::new())
+    }
+}
+
+impl<T: Zeroable> UnswapBox<T> {
+    /// Allocates a zeroed value and lets `f` fill it in place, so
+    /// it never exists outside of locked memory
+    pub fn try_init<F: FnOnce(&mut T)>(f: F) -> Result<Self, Error> {
+        Self::try_init_with(AllocOptions::new(), f)
+    }
+}
+
+impl<T, A: OsImpl> From<UnswapArray<T, A>> for UnswapBox<[T], A> {
+    /// Converts the array into a boxed slice, without copying
+    fn from(array: UnswapArray<T, A>) -> Self {
+        let array = ManuallyDrop::new(array);
+        // Derived from the pages rather than from a shared slice, as
+        // the box writes through it
+        let value = ptr::slice_from_raw_parts_mut(array.pages.as_ptr() as *mut T, array.len());
+        let value = NonNull::new(value).unwrap();
+        let pages = unsafe { ptr::read(&array.pages) };
+
+        Self { pages, value }
+    }
+}
+
+impl<T: Clone, A: OsImpl> UnswapBox<[T], A> {
+    /// Same as [UnswapBox::try_from_slice], but allocates the pages
+    /// from the `A` backend with the given `options`.
+    pub fn try_from_slice_with(options: AllocOptions, values: &[T]) -> Result<Self, Error> {
+        UnswapArray::try_from_fn_with(options, values.len(), |i| values[i].clone()).map(Self::from)
+    }
+}
+
+impl<T: Clone> UnswapBox<[T]> {
+    /// Copies `values` into locked memory
+    pub fn try_from_slice(values: &[T]) -> Result<Self, Error> {
+        Self::try_from_slice_with(AllocOptions::new(), values)
+    }
+}
+
+impl<A: OsImpl> UnswapBox<str, A> {
+    /// Same as [UnswapBox::try_from_str], but allocates the pages
+    /// from the `A` backend with the given `options`.
+    pub fn try_from_str_with(options: AllocOptions, text: &str) -> Result<Self, Error> {
+        let bytes = UnswapBox::<[u8], A>::try_from_slice_with(options, text.as_bytes())?;
+        let bytes = ManuallyDrop::new(bytes);
+        let value = NonNull::new(bytes.value.as_ptr() as *mut str).unwrap();
+
+        Ok(Self {
+            pages: unsafe { ptr::read(&bytes.pages) },
+            value,
+        })
+    }
+}
+
+impl UnswapBox<str> {
+    /// Copies `text` into locked memory
+    pub fn try_from_str(text: &str) -> Result<Self, Error> {
+        Self::try_from_str_with(AllocOptions::new(), text)
+    }
+}
+
+impl<T: Clone> From<&[T]> for UnswapBox<[T]> {
+    /// Copies `values` into locked memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked.
+    fn from(values: &[T]) -> Self {
+        Self::try_from_slice(values).expect("Failed to allocate locked memory pages")
+    }
+}
+
+impl From<&str> for UnswapBox<str> {
+    /// Copies `text` into locked memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked.
+    fn from(text: &str) -> Self {
+        Self::try_from_str(text).expect("Failed to allocate locked memory pages")
+    }
+}
+
+impl<T: ?Sized, A: OsImpl> UnswapBox<T, A> {
+    /// Returns the options the pages were allocated with
+    pub fn options(&self) -> &AllocOptions {
+        self.pages.options()
+    }
+}
+
+impl<T: ?Sized, A: OsImpl> Drop for UnswapBox<T, A> {
+    fn drop(&mut self) {
+        // The pages are wiped and released afterwards even if the
+        // destructor panics
+        unsafe {
+            ptr::drop_in_place(self.value.as_ptr());
+        }
+    }
+}
+
+impl<T: ?Sized, A: OsImpl> Deref for UnswapBox<T, A> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T: ?Sized, A: OsImpl> DerefMut for UnswapBox<T, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T: ?Sized, A: OsImpl> AsRef<T> for UnswapBox<T, A> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized, A: OsImpl> AsMut<T> for UnswapBox<T, A> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+use std::io;
+use std::str::Utf8Error;
+
+/// Errors related to allocating and locking memory buffers
+#[derive(Debug)]
+pub enum Error {
+    /// Size given was not properly aligned
+    AlignError,
+    /// The memory allocation routine failed
+    OsError {
+        /// Name of the system call which failed
+        call: &'static str,
+        /// Error reported by the OS
+        source: io::Error,
+    },
+    /// Requested array size overflows the address space
+    LayoutError,
+    /// Locking the pages would exceed the locked memory limit
+    /// (`RLIMIT_MEMLOCK`) of the process
+    LockLimit {
+        /// Name of the system call which failed
+        call: &'static str,
+        /// Error reported by the OS
+        source: io::Error,
+    },
+    /// Bytes given as text were not valid UTF-8
+    Utf8Error(Utf8Error),
+    /// The arena has no room left for the allocation
+    ArenaFull,
+    /// The locked memory pool has no room left for the allocation
+    PoolExhausted,
+}
+
+impl Error {
+    /// Returns the OS error code (`errno`) behind this error, if any
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            Error::OsError { source, .. } | Error::LockLimit { source, .. } => {
+                source.raw_os_error()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if raising `RLIMIT_MEMLOCK` may fix the error
+    pub fn is_lock_limit(&self) -> bool {
+        matches!(self, Error::LockLimit { .. })
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AlignError => f.write_str("size is not aligned to a page boundary"),
+            Error::OsError { call, source } => write!(f, "{} failed: {}", call, source),
+            Error::LayoutError => f.write_str("requested size overflows the address space"),
+            Error::LockLimit { call, source } => write!(
+                f,
+                "{} failed: {} (the locked memory limit is exhausted, \
+                 raise RLIMIT_MEMLOCK with `ulimit -l` or grant CAP_IPC_LOCK)",
+                call, source
+            ),
+            Error::Utf8Error(error) => write!(f, "invalid UTF-8: {}", error),
+            Error::ArenaFull => f.write_str("arena has no room left"),
+            Error::PoolExhausted => f.write_str("locked memory pool has no room left"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::OsError { source, .. } | Error::LockLimit { source, .. } => Some(source),
+            Error::Utf8Error(error) => Some(error),
+            _ => None,
+        }
+    }
+}
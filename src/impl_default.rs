@@ -0,0 +1,64 @@
+use crate::impl_secret::SecretImpl;
+use crate::impl_unix::UnixImpl;
+use crate::{AllocOptions, Backend, Error, OsImpl, Protection};
+use std::alloc::Layout;
+use std::ffi::c_void;
+
+/// Backend picking [UnixImpl] or [SecretImpl] for each allocation
+/// according to [AllocOptions::backend]
+pub struct DefaultImpl;
+
+impl DefaultImpl {
+    /// Returns the backend which actually serves allocations
+    /// made with `options`
+    pub fn backend(options: &AllocOptions) -> Backend {
+        match options.backend {
+            Backend::Mlock => Backend::Mlock,
+            Backend::MemfdSecret => SecretImpl::backend(),
+        }
+    }
+}
+
+unsafe impl OsImpl for DefaultImpl {
+    fn page_size() -> usize {
+        UnixImpl::page_size()
+    }
+
+    fn alloc_pages(layout: Layout, options: &AllocOptions) -> Result<*mut c_void, Error> {
+        match options.backend {
+            Backend::Mlock => UnixImpl::alloc_pages(layout, options),
+            Backend::MemfdSecret => SecretImpl::alloc_pages(layout, options),
+        }
+    }
+
+    unsafe fn free_pages(at: *mut c_void, layout: Layout, options: &AllocOptions) {
+        match options.backend {
+            Backend::Mlock => UnixImpl::free_pages(at, layout, options),
+            Backend::MemfdSecret => SecretImpl::free_pages(at, layout, options),
+        }
+    }
+
+    unsafe fn resize_pages(
+        at: *mut c_void,
+        layout: Layout,
+        options: &AllocOptions,
+        new_size: usize,
+    ) -> bool {
+        match options.backend {
+            Backend::Mlock => UnixImpl::resize_pages(at, layout, options, new_size),
+            Backend::MemfdSecret => SecretImpl::resize_pages(at, layout, options, new_size),
+        }
+    }
+
+    unsafe fn protect_pages(
+        at: *mut c_void,
+        layout: Layout,
+        options: &AllocOptions,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        match options.backend {
+            Backend::Mlock => UnixImpl::protect_pages(at, layout, options, protection),
+            Backend::MemfdSecret => SecretImpl::protect_pages(at, layout, options, protection),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::impl_unix::{last_error, UnixImpl};
+use crate::{AllocOptions, Backend, Error, OsImpl, Protection};
+use std::alloc::Layout;
+use std::ffi::c_void;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Backend allocating through `memfd_secret(2)`, which removes the
+/// pages from the kernel direct map.
+///
+/// Falls back to [UnixImpl] if the running kernel does not
+/// support secret memory.
+pub struct SecretImpl;
+
+const UNKNOWN: u8 = 0;
+const AVAILABLE: u8 = 1;
+const UNAVAILABLE: u8 = 2;
+
+/// Whether `memfd_secret(2)` is usable, [UNKNOWN] until first tried
+static STATE: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+impl SecretImpl {
+    /// Creates a new secret memory file descriptor.
+    ///
+    /// Returns `None` if secret memory is not supported by the
+    /// kernel or disabled in this process.
+    fn open() -> Result<Option<File>, Error> {
+        if STATE.load(Ordering::Relaxed) == UNAVAILABLE {
+            return Ok(None);
+        }
+        let fd = unsafe { libc::syscall(libc::SYS_memfd_secret, libc::O_CLOEXEC) };
+        if fd < 0 {
+            return match io::Error::last_os_error().raw_os_error() {
+                // Either missing from the kernel, disabled with
+                // secretmem.enable=0 or denied by a seccomp filter
+                Some(libc::ENOSYS) | Some(libc::EPERM) => {
+                    STATE.store(UNAVAILABLE, Ordering::Relaxed);
+                    Ok(None)
+                }
+                _ => Err(last_error("memfd_secret")),
+            };
+        }
+        STATE.store(AVAILABLE, Ordering::Relaxed);
+
+        Ok(Some(unsafe { File::from_raw_fd(fd as libc::c_int) }))
+    }
+
+    /// Returns the backend which actually serves allocations
+    /// made through [SecretImpl].
+    ///
+    /// Reports [Backend::Mlock] unless secret memory support has
+    /// been verified, e.g. if probing for it failed for lack of
+    /// file descriptors.
+    pub fn backend() -> Backend {
+        if STATE.load(Ordering::Relaxed) == UNKNOWN {
+            // The descriptor is only needed to probe for support
+            let _ = Self::open();
+        }
+        match STATE.load(Ordering::Relaxed) {
+            AVAILABLE => Backend::MemfdSecret,
+            _ => Backend::Mlock,
+        }
+    }
+}
+
+unsafe impl OsImpl for SecretImpl {
+    fn page_size() -> usize {
+        UnixImpl::page_size()
+    }
+
+    fn alloc_pages(layout: Layout, options: &AllocOptions) -> Result<*mut c_void, Error> {
+        let file = match Self::open()? {
+            Some(file) => file,
+            None => return UnixImpl::alloc_pages(layout, options),
+        };
+        let mut mapping = UnixImpl::reserve(layout, options)?;
+        file.set_len(layout.size() as u64)
+            .map_err(|source| Error::OsError {
+                call: "ftruncate",
+                source,
+            })?;
+
+        // Secret memory is locked and excluded from core dumps
+        // by the kernel, so neither mlock() nor MADV_DONTDUMP
+        // are needed here
+        mapping.map_fd(file.as_raw_fd())?;
+        mapping.apply_fork_policy(options.fork)?;
+
+        Ok(mapping.into_data())
+    }
+
+    unsafe fn free_pages(at: *mut c_void, layout: Layout, options: &AllocOptions) {
+        UnixImpl::free_pages(at, layout, options);
+    }
+
+    unsafe fn resize_pages(
+        at: *mut c_void,
+        layout: Layout,
+        options: &AllocOptions,
+        new_size: usize,
+    ) -> bool {
+        // The file behind the mapping is already closed, so it can
+        // only shrink, unless the region came from the fallback
+        if new_size > layout.size() && STATE.load(Ordering::Relaxed) != UNAVAILABLE {
+            return false;
+        }
+        UnixImpl::resize_pages(at, layout, options, new_size)
+    }
+
+    unsafe fn protect_pages(
+        at: *mut c_void,
+        layout: Layout,
+        options: &AllocOptions,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        UnixImpl::protect_pages(at, layout, options, protection)
+    }
+}
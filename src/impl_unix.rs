@@ -1,35 +1,295 @@
-use crate::{Error, OsImpl};
+use crate::{AllocOptions, Error, ForkPolicy, OsImpl, Protection};
+use std::alloc::Layout;
 use std::ffi::c_void;
+use std::io;
+use std::mem;
+use std::os::unix::io::RawFd;
 use std::ptr::null_mut;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-pub(crate) struct UnixImpl;
+/// Backend allocating anonymous mappings locked with `mlock(2)`
+pub struct UnixImpl;
 
-unsafe impl OsImpl for UnixImpl {
-    fn alloc_pages(size: usize) -> Result<*mut c_void, Error> {
-        if size & 0xFFF != 0 {
-            return Err(Error::AlignError);
-        }
-        let pages = unsafe {
+/// Page size reported by the OS, zero until first queried
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// Builds an [Error] from `errno` left by a failed `call`
+pub(crate) fn last_error(call: &'static str) -> Error {
+    let source = io::Error::last_os_error();
+    match (call, source.raw_os_error()) {
+        ("mlock", Some(libc::ENOMEM))
+        | ("mlock", Some(libc::EPERM))
+        | ("mmap", Some(libc::EAGAIN)) => Error::LockLimit { call, source },
+        _ => Error::OsError { call, source },
+    }
+}
+
+/// Anonymous mapping which is rolled back on drop unless
+/// released with [Mapping::into_data].
+///
+/// The usable part of the mapping starts `guard` bytes after
+/// `at`, and is followed by another `guard` bytes of inaccessible
+/// memory.
+pub(crate) struct Mapping {
+    at: *mut c_void,
+    size: usize,
+    guard: usize,
+    locked: bool,
+}
+
+impl Mapping {
+    fn new(size: usize, guard: usize) -> Result<Self, Error> {
+        let prot = if guard != 0 {
+            // Only the usable part is opened up later
+            libc::PROT_NONE
+        } else {
+            libc::PROT_READ | libc::PROT_WRITE
+        };
+        let at = unsafe {
             libc::mmap(
                 null_mut(),
                 size,
-                libc::PROT_READ | libc::PROT_WRITE,
+                prot,
                 libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
                 -1,
                 0,
             )
         };
-        if pages == libc::MAP_FAILED {
-            return Err(Error::OsError);
+        if at == libc::MAP_FAILED {
+            return Err(last_error("mmap"));
+        }
+
+        Ok(Self {
+            at,
+            size,
+            guard,
+            locked: false,
+        })
+    }
+
+    fn data(&self) -> *mut c_void {
+        unsafe { (self.at as *mut u8).add(self.guard) as *mut c_void }
+    }
+
+    fn data_size(&self) -> usize {
+        self.size - 2 * self.guard
+    }
+
+    /// Unmaps everything except `size` bytes starting at `offset`
+    fn trim(&mut self, offset: usize, size: usize) {
+        let tail = self.size - offset - size;
+        unsafe {
+            if offset != 0 {
+                libc::munmap(self.at, offset);
+            }
+            self.at = (self.at as *mut u8).add(offset) as *mut c_void;
+            if tail != 0 {
+                libc::munmap((self.at as *mut u8).add(size) as *mut c_void, tail);
+            }
+        }
+        self.size = size;
+    }
+
+    /// Makes the usable part readable and writable
+    fn open(&mut self) -> Result<(), Error> {
+        if self.guard == 0 {
+            return Ok(());
+        }
+        let prot = libc::PROT_READ | libc::PROT_WRITE;
+        if unsafe { libc::mprotect(self.data(), self.data_size(), prot) } != 0 {
+            return Err(last_error("mprotect"));
+        }
+        Ok(())
+    }
+
+    /// Replaces the usable part with a shared mapping of `fd`
+    pub(crate) fn map_fd(&mut self, fd: RawFd) -> Result<(), Error> {
+        let at = unsafe {
+            libc::mmap(
+                self.data(),
+                self.data_size(),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_FIXED,
+                fd,
+                0,
+            )
+        };
+        if at == libc::MAP_FAILED {
+            return Err(last_error("mmap"));
+        }
+        Ok(())
+    }
+
+    fn advise(&mut self, advice: libc::c_int) -> Result<(), Error> {
+        if unsafe { libc::madvise(self.data(), self.data_size(), advice) } != 0 {
+            return Err(last_error("madvise"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn apply_fork_policy(&mut self, fork: ForkPolicy) -> Result<(), Error> {
+        match fork {
+            ForkPolicy::Inherit => Ok(()),
+            ForkPolicy::WipeOnFork => self.advise(libc::MADV_WIPEONFORK),
+            ForkPolicy::DontFork => self.advise(libc::MADV_DONTFORK),
+        }
+    }
+
+    fn lock(&mut self) -> Result<(), Error> {
+        if unsafe { libc::mlock(self.data(), self.data_size()) } != 0 {
+            return Err(last_error("mlock"));
+        }
+        self.locked = true;
+        Ok(())
+    }
+
+    pub(crate) fn into_data(self) -> *mut c_void {
+        let data = self.data();
+        mem::forget(self);
+        data
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            if self.locked {
+                libc::munlock(self.data(), self.data_size());
+            }
+            libc::munmap(self.at, self.size);
+        }
+    }
+}
+
+impl UnixImpl {
+    fn guard_size(options: &AllocOptions) -> usize {
+        if options.guard_pages {
+            Self::page_size()
+        } else {
+            0
+        }
+    }
+
+    /// Maps a region for `layout`, surrounded by guard pages if
+    /// requested in `options`.
+    ///
+    /// If there are guard pages, the whole region stays
+    /// inaccessible until [Mapping::open] is called.
+    pub(crate) fn reserve(layout: Layout, options: &AllocOptions) -> Result<Mapping, Error> {
+        let size = layout.size();
+        if !Self::is_page_aligned(size) {
+            return Err(Error::AlignError);
+        }
+        let page_size = Self::page_size();
+        let guard = Self::guard_size(options);
+        let align = layout.align().max(page_size);
+
+        // mmap() only guarantees page alignment, so map enough
+        // extra pages to fit an aligned region and cut off the rest
+        let total = size
+            .checked_add(align - page_size)
+            .and_then(|total| total.checked_add(2 * guard))
+            .ok_or(Error::LayoutError)?;
+        let mut mapping = Mapping::new(total, guard)?;
+        let offset = (mapping.data() as usize).wrapping_neg() & (align - 1);
+        mapping.trim(offset, size + 2 * guard);
+
+        Ok(mapping)
+    }
+}
+
+unsafe impl OsImpl for UnixImpl {
+    fn page_size() -> usize {
+        let size = PAGE_SIZE.load(Ordering::Relaxed);
+        if size != 0 {
+            return size;
+        }
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        assert!(
+            size.is_power_of_two(),
+            "Invalid page size reported by the OS"
+        );
+        PAGE_SIZE.store(size, Ordering::Relaxed);
+        size
+    }
+
+    fn alloc_pages(layout: Layout, options: &AllocOptions) -> Result<*mut c_void, Error> {
+        let mut mapping = Self::reserve(layout, options)?;
+
+        mapping.open()?;
+        if options.dont_dump {
+            mapping.advise(libc::MADV_DONTDUMP)?;
+        }
+        mapping.apply_fork_policy(options.fork)?;
+        mapping.lock()?;
+
+        Ok(mapping.into_data())
+    }
+
+    unsafe fn free_pages(at: *mut c_void, layout: Layout, options: &AllocOptions) {
+        let guard = Self::guard_size(options);
+        libc::munmap(
+            (at as *mut u8).sub(guard) as *mut c_void,
+            layout.size() + 2 * guard,
+        );
+    }
+
+    unsafe fn resize_pages(
+        at: *mut c_void,
+        layout: Layout,
+        options: &AllocOptions,
+        new_size: usize,
+    ) -> bool {
+        if Self::guard_size(options) != 0 {
+            // The guard page after the region would have to move too
+            return false;
+        }
+        let size = layout.size();
+        // Without MREMAP_MAYMOVE the mapping either stays in place
+        // or the call fails
+        if libc::mremap(at, size, new_size, 0) == libc::MAP_FAILED {
+            return false;
+        }
+        if new_size <= size {
+            // The cut off pages are unlocked along with the unmapping
+            return true;
+        }
+
+        // The kernel extends the existing mapping with its flags, but
+        // apply them to the new pages anyway, so they never differ
+        // from the rest of the region. On failure the new pages are
+        // unmapped, which shrinks the region back.
+        let mut tail = Mapping {
+            at: (at as *mut u8).add(size) as *mut c_void,
+            size: new_size - size,
+            guard: 0,
+            locked: false,
+        };
+        if options.dont_dump && tail.advise(libc::MADV_DONTDUMP).is_err() {
+            return false;
         }
-        if unsafe { libc::mlock(pages, size) } != 0 {
-            return Err(Error::OsError);
+        if tail.apply_fork_policy(options.fork).is_err() || tail.lock().is_err() {
+            return false;
         }
+        tail.into_data();
 
-        Ok(pages)
+        true
     }
 
-    unsafe fn free_pages(at: *mut c_void, size: usize) {
-        libc::munmap(at, size);
+    unsafe fn protect_pages(
+        at: *mut c_void,
+        layout: Layout,
+        _options: &AllocOptions,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        let prot = match protection {
+            Protection::NoAccess => libc::PROT_NONE,
+            Protection::ReadOnly => libc::PROT_READ,
+            Protection::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+        };
+        if libc::mprotect(at, layout.size(), prot) != 0 {
+            return Err(last_error("mprotect"));
+        }
+        Ok(())
     }
 }
@@ -2,121 +2,557 @@
 //! protected from being swapped out in low-memory conditions,
 //! which is required for storing secret data.
 //!
-//! # Current problems
+//! # Small allocations
 //!
-//! Currently, the crate aligns all allocation sizes to
-//! a page boundary, which may be highly inefficient for
-//! making lots of small allocations. This, however, should not
-//! be a problem for typical use-cases: storing and
-//! manipulating private key data and other secrets.
+//! The containers align all allocation sizes to a page
+//! boundary, which may be highly inefficient for making lots
+//! of small allocations. For those, a [Slab] packs many small
+//! objects into shared locked pages.
 #![deny(missing_docs)]
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
 
 #[macro_use]
 extern crate cfg_if;
 
+use raw::RawPages;
 use std::alloc::Layout;
 use std::ffi::c_void;
+use std::iter::FromIterator;
 use std::marker::PhantomData;
-use std::mem::MaybeUninit;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
 use std::ops::{Deref, DerefMut};
+use std::ptr;
 use std::slice;
 
 /// OS-specific memory management trait
+///
+/// # Safety
+///
+/// Implementors must return regions which are valid for reads
+/// and writes of the requested size and which are actually
+/// protected from being swapped out.
 pub unsafe trait OsImpl {
-    /// Allocates a region of `size` bytes, aligned to a
-    /// page boundary and protected from being swapped out
-    /// onto a disk.
+    /// Returns the size of a memory page in bytes.
     ///
-    /// Will panic if `size` is not page-aligned.
-    fn alloc_pages(size: usize) -> Result<*mut c_void, Error>;
+    /// Must be a power of two and must not change during the
+    /// lifetime of the process.
+    fn page_size() -> usize;
+
+    /// Rounds `size` up to a multiple of the page size.
+    ///
+    /// Returns `None` if the result overflows `usize`.
+    fn page_align(size: usize) -> Option<usize> {
+        let mask = Self::page_size() - 1;
+        size.checked_add(mask).map(|size| size & !mask)
+    }
+
+    /// Returns `true` if `value` is a multiple of the page size
+    fn is_page_aligned(value: usize) -> bool {
+        value & (Self::page_size() - 1) == 0
+    }
+
+    /// Allocates a region described by `layout`, protected
+    /// from being swapped out onto a disk.
+    ///
+    /// The region is always aligned at least to a page boundary,
+    /// larger alignments are supported as well.
+    ///
+    /// Returns [Error::AlignError] if `layout.size()` is not
+    /// page-aligned. See [AllocOptions] for the other properties
+    /// of the region which can be requested.
+    fn alloc_pages(layout: Layout, options: &AllocOptions) -> Result<*mut c_void, Error>;
 
     /// Releases allocated pages back.
     ///
     /// # Safety
     ///
     /// The function is unsafe because it does not perform
-    /// validation of `at` and `size`, which needs to be
+    /// validation of `at` and `layout`, which needs to be
     /// done manually.
     ///
-    /// `at` must be a valid and properly (page-) aligned
-    /// address, `size` must be correct and aligned as well.
-    unsafe fn free_pages(at: *mut c_void, size: usize);
+    /// `at` must have been returned by [OsImpl::alloc_pages]
+    /// called with the same `layout` and `options`.
+    ///
+    /// The pages may be released unwiped and with any protection.
+    /// Backends which hand them out again rather than returning
+    /// them to the OS have to wipe them and make them writable.
+    unsafe fn free_pages(at: *mut c_void, layout: Layout, options: &AllocOptions);
+
+    /// Tries to change the size of allocated pages without moving
+    /// them.
+    ///
+    /// Returns `false` if the region cannot be resized in place, in
+    /// which case it is left untouched. The default implementation
+    /// never resizes.
+    ///
+    /// # Safety
+    ///
+    /// `at`, `layout` and `options` must describe a region returned
+    /// by [OsImpl::alloc_pages], and `new_size` must be a non-zero
+    /// multiple of the page size. On success, the region is
+    /// described by `layout` with its size replaced by `new_size`.
+    unsafe fn resize_pages(
+        at: *mut c_void,
+        layout: Layout,
+        options: &AllocOptions,
+        new_size: usize,
+    ) -> bool {
+        let _ = (at, layout, options, new_size);
+        false
+    }
+
+    /// Changes the access permissions of allocated pages.
+    ///
+    /// # Safety
+    ///
+    /// `at`, `layout` and `options` must describe a region returned
+    /// by [OsImpl::alloc_pages]. The caller must ensure no access
+    /// to the region is made which the new protection forbids.
+    unsafe fn protect_pages(
+        at: *mut c_void,
+        layout: Layout,
+        options: &AllocOptions,
+        protection: Protection,
+    ) -> Result<(), Error>;
 }
 
-/// Errors related to allocating and locking memory buffers
-#[derive(Debug)]
-pub enum Error {
-    /// Size given was not properly aligned
-    AlignError,
-    /// The memory allocation routine failed
-    OsError,
+/// Marker for types which are valid when all of their bytes are zero
+///
+/// # Safety
+///
+/// An all-zero bit pattern must be a valid value of the type.
+pub unsafe trait Zeroable {}
+
+macro_rules! impl_zeroable {
+    ($($ty:ty),*) => {
+        $(unsafe impl Zeroable for $ty {})*
+    };
 }
 
+impl_zeroable!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char
+);
+
+unsafe impl<T: Zeroable, const N: usize> Zeroable for [T; N] {}
+unsafe impl<T> Zeroable for MaybeUninit<T> {}
+
+mod allocator;
+mod arena;
+mod boxed;
+mod error;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod options;
+mod raw;
+mod sealed;
+mod slab;
+mod string;
+mod vec;
+
+pub use allocator::UnswapAlloc;
+pub use arena::UnswapArena;
+pub use boxed::UnswapBox;
+pub use error::Error;
+pub use options::{AllocOptions, Backend, ForkPolicy, Protection};
+pub use sealed::{ReadGuard, SealedArray, WriteGuard};
+pub use slab::{Slab, SlabBox};
+pub use string::{FromUtf8Error, UnswapString};
+pub use vec::UnswapVec;
+
 cfg_if! {
     if #[cfg(target_os = "linux")] {
         extern crate libc;
 
+        mod impl_default;
+        mod impl_secret;
         mod impl_unix;
-        use impl_unix::UnixImpl as Impl;
+        pub mod memlock;
+        pub mod pool;
+
+        pub use impl_default::DefaultImpl;
+        pub use impl_secret::SecretImpl;
+        pub use impl_unix::UnixImpl;
     }
 }
 
 /// Array residing in non-swappable memory
-pub struct UnswapArray<T> {
-    data: *mut c_void,
+///
+/// The pages are provided by the `A` backend, see [OsImpl].
+pub struct UnswapArray<T, A: OsImpl = DefaultImpl> {
+    pages: RawPages<A>,
     len: usize,
-    size: usize,
     _pd: PhantomData<T>,
 }
 
-/// Container for unswappable data
+unsafe impl<T: Send, A: OsImpl> Send for UnswapArray<T, A> {}
+unsafe impl<T: Sync, A: OsImpl> Sync for UnswapArray<T, A> {}
+
+impl<T, A: OsImpl> UnswapArray<T, A> {
+    /// Allocates an empty array with room for `len` elements
+    fn try_alloc(options: AllocOptions, len: usize) -> Result<Self, Error> {
+        Ok(Self {
+            pages: RawPages::try_alloc_array::<T>(options, len)?,
+            len: 0,
+            _pd: PhantomData,
+        })
+    }
+
+    /// Same as [UnswapArray::try_from_fn], but allocates the pages
+    /// from the `A` backend with the given `options`.
+    pub fn try_from_fn_with<F: FnMut(usize) -> T>(
+        options: AllocOptions,
+        len: usize,
+        mut f: F,
+    ) -> Result<Self, Error> {
+        let mut array = Self::try_alloc(options, len)?;
+        let slots: &mut [MaybeUninit<T>] =
+            unsafe { slice::from_raw_parts_mut(array.pages.as_ptr() as *mut MaybeUninit<T>, len) };
+        for (i, uninit) in slots.iter_mut().enumerate() {
+            uninit.write(f(i));
+            array.len += 1;
+        }
+
+        Ok(array)
+    }
+
+    /// Same as [UnswapArray::try_from_iter], but allocates the
+    /// pages from the `A` backend with the given `options`.
+    pub fn try_from_iter_with<I>(options: AllocOptions, iter: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+        let len = iter.len();
+        let mut array = Self::try_alloc(options, len)?;
+        // Never trust the reported length past the allocated room,
+        // an iterator yielding fewer elements just makes the array
+        // shorter
+        for value in iter.take(len) {
+            unsafe {
+                ptr::write((array.pages.as_ptr() as *mut T).add(array.len), value);
+            }
+            array.len += 1;
+        }
+
+        Ok(array)
+    }
+
+    /// Same as [UnswapArray::try_new_uninit], but allocates the
+    /// pages from the `A` backend with the given `options`.
+    pub fn try_new_uninit_with(
+        options: AllocOptions,
+        len: usize,
+    ) -> Result<UnswapArray<MaybeUninit<T>, A>, Error> {
+        let mut array = UnswapArray::try_alloc(options, len)?;
+        array.len = len;
+        Ok(array)
+    }
+
+    /// Returns the options the array pages were allocated with
+    pub fn options(&self) -> &AllocOptions {
+        self.pages.options()
+    }
+
+    /// Drops all the elements and overwrites the whole underlying
+    /// memory region with zeroes, leaving the array empty.
+    ///
+    /// The pages themselves stay allocated and locked until the
+    /// array is dropped.
+    pub fn wipe(&mut self) {
+        let elements: *mut [T] = &mut **self;
+        // Set the length first so a panicking destructor cannot
+        // cause a double drop
+        self.len = 0;
+        unsafe {
+            ptr::drop_in_place(elements);
+        }
+        self.pages.wipe();
+    }
+
+    /// Drops the elements past `len` and wipes the memory they
+    /// occupied, keeping the pages.
+    ///
+    /// Does nothing if `len` is not less than the current length.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let size = (self.len - len) * mem::size_of::<T>();
+        let tail: *mut [T] = &mut self[len..];
+        // Set the length first so a panicking destructor cannot
+        // cause a double drop
+        self.len = len;
+        unsafe {
+            ptr::drop_in_place(tail);
+            raw::wipe_bytes(tail as *mut u8, size);
+        }
+    }
+
+    /// Takes the pages out of the array, without dropping the
+    /// elements
+    pub(crate) fn into_raw_pages(self) -> RawPages<A> {
+        let this = ManuallyDrop::new(self);
+        unsafe { ptr::read(&this.pages) }
+    }
+
+    /// Releases the pages past the ones holding the elements.
+    ///
+    /// The pages are cut off in place where the backend allows it,
+    /// otherwise the elements are moved to a new, smaller region.
+    /// If that cannot be allocated, the array is left as is.
+    pub fn shrink_to_fit(&mut self) {
+        self.pages.shrink_to::<T>(self.len);
+    }
+}
+
+impl<T> UnswapArray<T> {
+    /// Allocates a new array for `len` elements, initializing
+    /// each one with the value returned by `f` for its index.
+    ///
+    /// If `f` panics, the elements produced so far are dropped
+    /// and the pages are released.
+    pub fn try_from_fn<F: FnMut(usize) -> T>(len: usize, f: F) -> Result<Self, Error> {
+        Self::try_from_fn_with(AllocOptions::new(), len, f)
+    }
+
+    /// Allocates a new array for `len` elements, initializing
+    /// each one with the value returned by `f` for its index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked,
+    /// see [UnswapArray::try_from_fn] for a non-panicking version.
+    pub fn from_fn<F: FnMut(usize) -> T>(len: usize, f: F) -> Self {
+        Self::try_from_fn(len, f).expect("Failed to allocate locked memory pages")
+    }
+
+    /// Allocates a new array holding the elements of `iter`.
+    ///
+    /// The room is allocated once, for as many elements as the
+    /// iterator reports.
+    pub fn try_from_iter<I>(iter: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        Self::try_from_iter_with(AllocOptions::new(), iter)
+    }
+
+    /// Allocates a new array for `len` elements, leaving them
+    /// uninitialized, so they can be written in place:
+    ///
+    /// ```
+    /// use unswap::UnswapArray;
+    ///
+    /// let mut array = UnswapArray::<u32>::try_new_uninit(4).unwrap();
+    /// for (i, slot) in array.iter_mut().enumerate() {
+    ///     slot.write(i as u32);
+    /// }
+    /// let array = unsafe { array.assume_init() };
+    /// assert_eq!(&*array, &[0, 1, 2, 3]);
+    /// ```
+    pub fn try_new_uninit(len: usize) -> Result<UnswapArray<MaybeUninit<T>>, Error> {
+        Self::try_new_uninit_with(AllocOptions::new(), len)
+    }
+
+    /// Allocates a new array for `len` elements, leaving them
+    /// uninitialized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked,
+    /// see [UnswapArray::try_new_uninit] for a non-panicking version.
+    pub fn new_uninit(len: usize) -> UnswapArray<MaybeUninit<T>> {
+        Self::try_new_uninit(len).expect("Failed to allocate locked memory pages")
+    }
+
+    /// Returns the backend which actually holds the array pages
+    pub fn backend(&self) -> Backend {
+        DefaultImpl::backend(self.pages.options())
+    }
+}
+
+impl<T, A: OsImpl> UnswapArray<MaybeUninit<T>, A> {
+    /// Converts to `UnswapArray<T>` once the elements are
+    /// initialized.
+    ///
+    /// # Safety
+    ///
+    /// All the elements must have been initialized.
+    pub unsafe fn assume_init(self) -> UnswapArray<T, A> {
+        let this = ManuallyDrop::new(self);
+        UnswapArray {
+            pages: ptr::read(&this.pages),
+            len: this.len,
+            _pd: PhantomData,
+        }
+    }
+}
+
+impl<T: Zeroable, A: OsImpl> UnswapArray<T, A> {
+    /// Same as [UnswapArray::try_zeroed], but allocates the pages
+    /// from the `A` backend with the given `options`.
+    pub fn try_zeroed_with(options: AllocOptions, len: usize) -> Result<Self, Error> {
+        let mut array = Self::try_alloc(options, len)?;
+        unsafe {
+            ptr::write_bytes(array.pages.as_ptr() as *mut T, 0, len);
+        }
+        array.len = len;
+
+        Ok(array)
+    }
+}
+
+impl<T: Zeroable> UnswapArray<T> {
+    /// Allocates a new array of `len` zero-initialized elements
+    pub fn try_zeroed(len: usize) -> Result<Self, Error> {
+        Self::try_zeroed_with(AllocOptions::new(), len)
+    }
+
+    /// Allocates a new array of `len` zero-initialized elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked,
+    /// see [UnswapArray::try_zeroed] for a non-panicking version.
+    pub fn zeroed(len: usize) -> Self {
+        Self::try_zeroed(len).expect("Failed to allocate locked memory pages")
+    }
+}
+
+impl<T: Clone, A: OsImpl> UnswapArray<T, A> {
+    /// Same as [UnswapArray::try_new], but allocates the pages
+    /// from the `A` backend with the given `options`.
+    ///
+    /// The backend is picked by the type of the result:
+    ///
+    /// ```
+    /// use unswap::{AllocOptions, UnixImpl, UnswapArray};
+    ///
+    /// let array = UnswapArray::<u8, UnixImpl>::try_new_with(AllocOptions::new(), 0, 32);
+    /// assert_eq!(array.unwrap().len(), 32);
+    /// ```
+    pub fn try_new_with(options: AllocOptions, value: T, len: usize) -> Result<Self, Error> {
+        Self::try_from_fn_with(options, len, |_| value.clone())
+    }
+}
+
+impl<T: Clone, A: OsImpl> UnswapArray<T, A> {
+    /// Changes the array length to `new_len`, either dropping the
+    /// elements past it or appending clones of `fill`.
+    ///
+    /// Growing extends the locked region in place where the backend
+    /// allows it. Otherwise the elements are moved to a new region,
+    /// and the old one is wiped and released. On failure the array
+    /// is left unchanged.
+    ///
+    /// Shrinking keeps the pages, see [UnswapArray::shrink_to_fit].
+    pub fn try_resize(&mut self, new_len: usize, fill: T) -> Result<(), Error> {
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return Ok(());
+        }
+        self.pages.try_grow::<T>(self.len, new_len)?;
+
+        let end = self.pages.as_ptr() as *mut T;
+        while self.len < new_len - 1 {
+            unsafe {
+                ptr::write(end.add(self.len), fill.clone());
+            }
+            self.len += 1;
+        }
+        unsafe {
+            ptr::write(end.add(self.len), fill);
+        }
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// Changes the array length to `new_len`, either dropping the
+    /// elements past it or appending clones of `fill`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked,
+    /// see [UnswapArray::try_resize] for a non-panicking version.
+    pub fn resize(&mut self, new_len: usize, fill: T) {
+        self.try_resize(new_len, fill)
+            .expect("Failed to allocate locked memory pages")
+    }
+}
 
 impl<T: Clone> UnswapArray<T> {
     /// Allocates a new array for `len` elements of type `T`.
     ///
-    /// The resulting array is page-aligned.
+    /// The resulting array is aligned to a page boundary or
+    /// to the alignment of `T`, whichever is larger.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked,
+    /// see [UnswapArray::try_new] for a non-panicking version.
     pub fn new(value: T, len: usize) -> Self {
-        let layout = Layout::array::<T>(len).unwrap();
-        if layout.align() > 0x1000 {
-            unimplemented!();
-        }
-        let size = (layout.size() + 0xFFF) & !0xFFF;
-        let data = Impl::alloc_pages(size).expect("Failed to allocate locked memory pages");
+        Self::try_new(value, len).expect("Failed to allocate locked memory pages")
+    }
 
-        let array: &mut [MaybeUninit<T>] =
-            unsafe { slice::from_raw_parts_mut(data as *mut MaybeUninit<T>, len) };
-        for uninit in array.iter_mut() {
-            uninit.write(value.clone());
-        }
+    /// Allocates a new array for `len` clones of `value`.
+    ///
+    /// If `clone()` panics, only the elements written so far
+    /// are dropped along with the array.
+    pub fn try_new(value: T, len: usize) -> Result<Self, Error> {
+        Self::try_new_with(AllocOptions::new(), value, len)
+    }
+}
+
+impl<T> FromIterator<T> for UnswapArray<T> {
+    /// Collects the elements into locked memory, growing it as
+    /// needed, see [UnswapArray::try_from_iter] to allocate once
+    /// for exact-size iterators.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().collect::<UnswapVec<T>>().into()
+    }
+}
 
+impl<T, A: OsImpl> From<UnswapVec<T, A>> for UnswapArray<T, A> {
+    /// Converts the vector into an array, without copying
+    fn from(vec: UnswapVec<T, A>) -> Self {
+        let (pages, len) = vec.into_raw_parts();
         Self {
-            data,
+            pages,
             len,
-            size,
             _pd: PhantomData,
         }
     }
 }
 
-impl<T> Drop for UnswapArray<T> {
+impl<T, A: OsImpl> Drop for UnswapArray<T, A> {
     fn drop(&mut self) {
+        // The pages are wiped and released afterwards even if one
+        // of the element destructors panics
+        let elements: *mut [T] = &mut **self;
         unsafe {
-            Impl::free_pages(self.data, self.size);
+            ptr::drop_in_place(elements);
         }
     }
 }
 
-impl<T> Deref for UnswapArray<T> {
+impl<T, A: OsImpl> Deref for UnswapArray<T, A> {
     type Target = [T];
 
     fn deref(&self) -> &Self::Target {
-        unsafe { slice::from_raw_parts(self.data as *const T, self.len) }
+        unsafe { slice::from_raw_parts(self.pages.as_ptr() as *const T, self.len) }
     }
 }
 
-impl<T> DerefMut for UnswapArray<T> {
+impl<T, A: OsImpl> DerefMut for UnswapArray<T, A> {
     #[inline(always)]
     fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { slice::from_raw_parts_mut(self.data as *mut T, self.len) }
+        unsafe { slice::from_raw_parts_mut(self.pages.as_ptr() as *mut T, self.len) }
     }
 }
@@ -0,0 +1,152 @@
+//! Inspecting and raising the locked memory limit.
+//!
+//! Every locked page counts towards `RLIMIT_MEMLOCK`, which is
+//! often as low as 64 KiB or 8 MiB. These helpers tell how much of
+//! it is left, so allocations can be planned (or the limit raised)
+//! before they start failing with [Error::LockLimit].
+//!
+//! ```
+//! use unswap::memlock;
+//!
+//! // Use whatever the hard limit allows
+//! let limit = memlock::raise_limit().unwrap();
+//! assert_eq!(limit.soft, limit.hard);
+//!
+//! if let Some(count) = memlock::estimate_fit(32).unwrap() {
+//!     println!("room for {} more 32-byte keys", count);
+//! }
+//! ```
+use crate::impl_unix::{last_error, UnixImpl};
+use crate::{Error, OsImpl};
+use std::fs;
+use std::io;
+use std::mem::MaybeUninit;
+
+/// `CAP_IPC_LOCK` capability bit, which lifts the limit entirely
+const CAP_IPC_LOCK: u32 = 14;
+
+/// Soft and hard `RLIMIT_MEMLOCK` of the process, in bytes, where
+/// `None` means unlimited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limit {
+    /// Limit currently enforced
+    pub soft: Option<usize>,
+    /// Ceiling the soft limit can be raised to without privileges
+    pub hard: Option<usize>,
+}
+
+fn from_rlim(value: libc::rlim_t) -> Option<usize> {
+    match value {
+        libc::RLIM_INFINITY => None,
+        value => Some(value as usize),
+    }
+}
+
+fn get_rlimit() -> Result<libc::rlimit, Error> {
+    let mut rlimit = MaybeUninit::<libc::rlimit>::uninit();
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, rlimit.as_mut_ptr()) } != 0 {
+        return Err(last_error("getrlimit"));
+    }
+    Ok(unsafe { rlimit.assume_init() })
+}
+
+/// Reads the process status, returning the value of `field`
+fn status_field(field: &str) -> Result<String, Error> {
+    let status = fs::read_to_string("/proc/self/status").map_err(|source| Error::OsError {
+        call: "read",
+        source,
+    })?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))
+        .map(|value| value.trim().to_owned())
+        .ok_or_else(|| Error::OsError {
+            call: "read",
+            source: io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} missing from /proc/self/status", field),
+            ),
+        })
+}
+
+/// Returns the current locked memory limits
+pub fn limit() -> Result<Limit, Error> {
+    let rlimit = get_rlimit()?;
+    Ok(Limit {
+        soft: from_rlim(rlimit.rlim_cur),
+        hard: from_rlim(rlimit.rlim_max),
+    })
+}
+
+/// Raises the soft limit up to the hard one, returning the limits
+/// in effect afterwards.
+///
+/// Going beyond the hard limit requires `CAP_SYS_RESOURCE`, so it
+/// has to be done by whoever starts the process, e.g. with
+/// `ulimit -l` or `LimitMEMLOCK=` in a systemd unit.
+pub fn raise_limit() -> Result<Limit, Error> {
+    let mut rlimit = get_rlimit()?;
+    if rlimit.rlim_cur != rlimit.rlim_max {
+        rlimit.rlim_cur = rlimit.rlim_max;
+        if unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &rlimit) } != 0 {
+            return Err(last_error("setrlimit"));
+        }
+    }
+
+    limit()
+}
+
+/// Returns the number of bytes currently locked by the process,
+/// as reported by `VmLck` in `/proc/self/status`
+pub fn locked_bytes() -> Result<usize, Error> {
+    let value = status_field("VmLck")?;
+    value
+        .strip_suffix("kB")
+        .and_then(|kib| kib.trim().parse::<usize>().ok())
+        .map(|kib| kib * 1024)
+        .ok_or_else(|| Error::OsError {
+            call: "read",
+            source: io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid VmLck value: {}", value),
+            ),
+        })
+}
+
+/// Returns `true` if the process has `CAP_IPC_LOCK`, which makes
+/// the limit not apply to it
+pub fn is_unrestricted() -> Result<bool, Error> {
+    let value = status_field("CapEff")?;
+    let caps = u64::from_str_radix(&value, 16).map_err(|_| Error::OsError {
+        call: "read",
+        source: io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid CapEff value: {}", value),
+        ),
+    })?;
+    Ok(caps & (1 << CAP_IPC_LOCK) != 0)
+}
+
+/// Returns the number of bytes which can still be locked, or
+/// `None` if there is no limit
+pub fn available() -> Result<Option<usize>, Error> {
+    if is_unrestricted()? {
+        return Ok(None);
+    }
+    match limit()?.soft {
+        Some(soft) => Ok(Some(soft.saturating_sub(locked_bytes()?))),
+        None => Ok(None),
+    }
+}
+
+/// Estimates how many more allocations of `size` bytes fit in the
+/// limit, or `None` if there is no limit.
+///
+/// Each allocation is counted as whole pages, as made by the
+/// containers of this crate. Guard pages are never locked, so
+/// they are not counted. Allocations packed by a [Slab](crate::Slab)
+/// take less.
+pub fn estimate_fit(size: usize) -> Result<Option<usize>, Error> {
+    let size = UnixImpl::page_align(size.max(1)).ok_or(Error::LayoutError)?;
+    Ok(available()?.map(|available| available / size))
+}
@@ -0,0 +1,339 @@
+//! Deterministic in-process backend for tests.
+//!
+//! [MockImpl] never issues system calls: pages come from the
+//! global allocator, and locking and protection changes are only
+//! simulated, which makes it usable under Miri and in sandboxes
+//! where `mlock(2)` is forbidden.
+//!
+//! The simulated state is kept per thread, so tests running in
+//! parallel do not affect each other.
+//!
+//! ```
+//! use unswap::mock::{self, Call, MockImpl};
+//! use unswap::{AllocOptions, UnswapArray};
+//!
+//! mock::reset();
+//! mock::set_memlock_limit(Some(0x2000));
+//!
+//! let array = UnswapArray::<u8, MockImpl>::try_new_with(AllocOptions::new(), 0, 0x2000);
+//! assert!(array.is_ok());
+//! let array = UnswapArray::<u8, MockImpl>::try_new_with(AllocOptions::new(), 0, 1);
+//! assert!(array.err().unwrap().is_lock_limit());
+//!
+//! assert!(matches!(mock::calls()[1], Call::Alloc { at: None, .. }));
+//! ```
+use crate::{AllocOptions, Error, OsImpl, Protection};
+use std::alloc::{self, Layout};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::c_void;
+use std::io;
+
+/// Backend simulating locked page allocation in-process
+pub struct MockImpl;
+
+/// Backend call recorded by [MockImpl]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Call {
+    /// [OsImpl::alloc_pages] was called, `at` is the address of
+    /// the returned region or `None` if the call failed
+    Alloc {
+        /// Requested layout
+        layout: Layout,
+        /// Requested options
+        options: AllocOptions,
+        /// Address of the allocated region
+        at: Option<usize>,
+    },
+    /// [OsImpl::free_pages] was called
+    Free {
+        /// Address of the released region
+        at: usize,
+        /// Layout of the released region
+        layout: Layout,
+        /// Whether the region held only zeroes when released
+        wiped: bool,
+    },
+    /// [OsImpl::protect_pages] was called
+    Protect {
+        /// Address of the region
+        at: usize,
+        /// Layout of the region
+        layout: Layout,
+        /// Requested protection
+        protection: Protection,
+    },
+}
+
+struct Region {
+    /// Pointer returned by the global allocator, which may access
+    /// the whole region
+    data: *mut u8,
+    layout: Layout,
+    protection: Protection,
+}
+
+struct State {
+    page_size: usize,
+    memlock_limit: Option<usize>,
+    locked: usize,
+    map_count: usize,
+    lock_count: usize,
+    protect_count: usize,
+    fail_map_on: Option<usize>,
+    fail_lock_on: Option<usize>,
+    fail_protect_on: Option<usize>,
+    regions: BTreeMap<usize, Region>,
+    calls: Vec<Call>,
+}
+
+impl State {
+    const fn new() -> Self {
+        Self {
+            page_size: 0x1000,
+            memlock_limit: None,
+            locked: 0,
+            map_count: 0,
+            lock_count: 0,
+            protect_count: 0,
+            fail_map_on: None,
+            fail_lock_on: None,
+            fail_protect_on: None,
+            regions: BTreeMap::new(),
+            calls: Vec::new(),
+        }
+    }
+
+    /// Simulates mmap() + mlock() of a region
+    fn alloc(&mut self, layout: Layout) -> Result<*mut c_void, Error> {
+        if layout.size() == 0 || layout.size() & (self.page_size - 1) != 0 {
+            return Err(Error::AlignError);
+        }
+
+        self.map_count += 1;
+        if self.fail_map_on == Some(self.map_count) {
+            return Err(Error::OsError {
+                call: "mmap",
+                source: io::Error::from_raw_os_error(libc::ENOMEM),
+            });
+        }
+
+        self.lock_count += 1;
+        if self.fail_lock_on == Some(self.lock_count) {
+            return Err(Error::OsError {
+                call: "mlock",
+                source: io::Error::from_raw_os_error(libc::EAGAIN),
+            });
+        }
+        let locked = self.locked + layout.size();
+        if self.memlock_limit.is_some_and(|limit| locked > limit) {
+            return Err(Error::LockLimit {
+                call: "mlock",
+                source: io::Error::from_raw_os_error(libc::ENOMEM),
+            });
+        }
+
+        let real_layout = layout
+            .align_to(self.page_size)
+            .map_err(|_| Error::LayoutError)?;
+        let at = unsafe { alloc::alloc_zeroed(real_layout) };
+        if at.is_null() {
+            alloc::handle_alloc_error(real_layout);
+        }
+        self.locked = locked;
+        self.regions.insert(
+            at as usize,
+            Region {
+                data: at,
+                layout,
+                protection: Protection::ReadWrite,
+            },
+        );
+
+        Ok(at as *mut c_void)
+    }
+
+    fn free(&mut self, at: *mut c_void, layout: Layout) {
+        let region = self
+            .regions
+            .remove(&(at as usize))
+            .expect("Freeing a region not allocated by MockImpl");
+        assert_eq!(region.layout, layout, "Freeing a region with wrong layout");
+
+        self.locked -= layout.size();
+        unsafe {
+            alloc::dealloc(at as *mut u8, layout.align_to(self.page_size).unwrap());
+        }
+    }
+
+    fn protect(
+        &mut self,
+        at: *mut c_void,
+        layout: Layout,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        let region = self
+            .regions
+            .get_mut(&(at as usize))
+            .expect("Protecting a region not allocated by MockImpl");
+        assert_eq!(
+            region.layout, layout,
+            "Protecting a region with wrong layout"
+        );
+
+        self.protect_count += 1;
+        if self.fail_protect_on == Some(self.protect_count) {
+            return Err(Error::OsError {
+                call: "mprotect",
+                source: io::Error::from_raw_os_error(libc::EACCES),
+            });
+        }
+        region.protection = protection;
+        Ok(())
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<State> = const { RefCell::new(State::new()) };
+}
+
+fn with_state<R, F: FnOnce(&mut State) -> R>(f: F) -> R {
+    STATE.with(|state| f(&mut state.borrow_mut()))
+}
+
+/// Restores the default settings and clears the recorded calls
+/// and injected failures.
+///
+/// # Panics
+///
+/// Panics if some regions allocated on this thread are still
+/// live.
+pub fn reset() {
+    with_state(|state| {
+        assert!(state.regions.is_empty(), "MockImpl regions still allocated");
+        *state = State::new();
+    })
+}
+
+/// Sets the simulated page size, 4 KiB by default.
+///
+/// # Panics
+///
+/// Panics if `size` is not a power of two or if some regions
+/// allocated on this thread are still live.
+pub fn set_page_size(size: usize) {
+    assert!(size.is_power_of_two(), "Page size must be a power of two");
+    with_state(|state| {
+        assert!(state.regions.is_empty(), "MockImpl regions still allocated");
+        state.page_size = size;
+    })
+}
+
+/// Sets the simulated `RLIMIT_MEMLOCK`, `None` (the default)
+/// means no limit
+pub fn set_memlock_limit(limit: Option<usize>) {
+    with_state(|state| state.memlock_limit = limit)
+}
+
+/// Makes the `n`-th `mmap()` from now on fail with `ENOMEM`,
+/// counting from 1
+pub fn fail_mmap_on(n: usize) {
+    with_state(|state| state.fail_map_on = Some(state.map_count + n))
+}
+
+/// Makes the `n`-th `mlock()` from now on fail with `EAGAIN`,
+/// counting from 1
+pub fn fail_mlock_on(n: usize) {
+    with_state(|state| state.fail_lock_on = Some(state.lock_count + n))
+}
+
+/// Makes the `n`-th `mprotect()` from now on fail with `EACCES`,
+/// counting from 1
+pub fn fail_mprotect_on(n: usize) {
+    with_state(|state| state.fail_protect_on = Some(state.protect_count + n))
+}
+
+/// Returns the number of bytes currently locked
+pub fn locked_bytes() -> usize {
+    with_state(|state| state.locked)
+}
+
+/// Returns the number of live regions
+pub fn region_count() -> usize {
+    with_state(|state| state.regions.len())
+}
+
+/// Returns the current protection of the region at `at`
+pub fn protection(at: *const c_void) -> Option<Protection> {
+    with_state(|state| {
+        state
+            .regions
+            .get(&(at as usize))
+            .map(|region| region.protection)
+    })
+}
+
+/// Returns a copy of the whole region at `at`, or `None` if there
+/// is no such region.
+///
+/// Unlike reading through the pointers handed out by containers,
+/// this may look past their length, e.g. to check that removed
+/// elements were wiped.
+pub fn contents(at: *const c_void) -> Option<Vec<u8>> {
+    with_state(|state| {
+        state.regions.get(&(at as usize)).map(|region| unsafe {
+            std::slice::from_raw_parts(region.data, region.layout.size()).to_vec()
+        })
+    })
+}
+
+/// Returns the calls recorded since the last [reset]
+pub fn calls() -> Vec<Call> {
+    with_state(|state| state.calls.clone())
+}
+
+unsafe impl OsImpl for MockImpl {
+    fn page_size() -> usize {
+        with_state(|state| state.page_size)
+    }
+
+    fn alloc_pages(layout: Layout, options: &AllocOptions) -> Result<*mut c_void, Error> {
+        with_state(|state| {
+            let result = state.alloc(layout);
+            state.calls.push(Call::Alloc {
+                layout,
+                options: *options,
+                at: result.as_ref().ok().map(|&at| at as usize),
+            });
+            result
+        })
+    }
+
+    unsafe fn free_pages(at: *mut c_void, layout: Layout, _options: &AllocOptions) {
+        with_state(|state| {
+            let bytes = std::slice::from_raw_parts(at as *const u8, layout.size());
+            state.calls.push(Call::Free {
+                at: at as usize,
+                layout,
+                wiped: bytes.iter().all(|&byte| byte == 0),
+            });
+            state.free(at, layout);
+        })
+    }
+
+    unsafe fn protect_pages(
+        at: *mut c_void,
+        layout: Layout,
+        _options: &AllocOptions,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        with_state(|state| {
+            state.calls.push(Call::Protect {
+                at: at as usize,
+                layout,
+                protection,
+            });
+            state.protect(at, layout, protection)
+        })
+    }
+}
@@ -0,0 +1,87 @@
+/// What a child process created by `fork()` sees in place of
+/// the region
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForkPolicy {
+    /// The child gets a copy-on-write view of the region,
+    /// same as for any other memory
+    Inherit,
+    /// The region is mapped in the child, but filled with zeroes.
+    ///
+    /// Not supported by [Backend::MemfdSecret], whose pages are
+    /// shared with the child unless [ForkPolicy::DontFork] is used.
+    WipeOnFork,
+    /// The region is not mapped in the child at all, so any
+    /// access to it faults
+    DontFork,
+}
+
+/// Mechanism used to keep the pages out of swap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Anonymous mapping locked with `mlock(2)`
+    Mlock,
+    /// Secret memory from `memfd_secret(2)`, which is also removed
+    /// from the kernel direct map. Falls back to [Backend::Mlock]
+    /// on kernels without secret memory support.
+    MemfdSecret,
+}
+
+/// Access permissions of allocated pages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protection {
+    /// Any access to the pages faults
+    NoAccess,
+    /// The pages can only be read
+    ReadOnly,
+    /// The pages can be read and written
+    ReadWrite,
+}
+
+/// Per-allocation settings passed to [OsImpl::alloc_pages]
+///
+/// [OsImpl::alloc_pages]: crate::OsImpl::alloc_pages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocOptions {
+    /// Surround the region with inaccessible guard pages, so
+    /// a linear overrun faults immediately instead of reaching
+    /// neighbouring memory.
+    ///
+    /// Enabled by default in debug builds.
+    pub guard_pages: bool,
+    /// Exclude the region from core dumps of the process.
+    ///
+    /// Enabled by default, may be turned off when debugging.
+    /// [Backend::MemfdSecret] pages are always excluded.
+    pub dont_dump: bool,
+    /// What child processes see in place of the region.
+    ///
+    /// Defaults to [ForkPolicy::Inherit].
+    pub fork: ForkPolicy,
+    /// Preferred mechanism for locking the pages.
+    ///
+    /// Defaults to [Backend::MemfdSecret] if the `memfd-secret`
+    /// feature is enabled, [Backend::Mlock] otherwise.
+    pub backend: Backend,
+}
+
+impl AllocOptions {
+    /// Returns the default options
+    pub const fn new() -> Self {
+        Self {
+            guard_pages: cfg!(debug_assertions),
+            dont_dump: true,
+            fork: ForkPolicy::Inherit,
+            backend: if cfg!(feature = "memfd-secret") {
+                Backend::MemfdSecret
+            } else {
+                Backend::Mlock
+            },
+        }
+    }
+}
+
+impl Default for AllocOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,270 @@
+//! Locked memory reserved once at startup.
+//!
+//! [PoolImpl] serves allocations out of a single region locked by
+//! [init], so they cannot fail later because other threads or
+//! processes exhausted `RLIMIT_MEMLOCK` in the meantime. What
+//! happens once the pool runs out is decided by its [Policy].
+//!
+//! The region is carved into whole pages and is never released.
+//! The [AllocOptions] of each allocation only matter when it falls
+//! back to [DefaultImpl], the pool pages themselves get the
+//! options given to [init].
+//!
+//! ```
+//! use unswap::pool::{self, Policy, PoolImpl};
+//! use unswap::{AllocOptions, UnswapArray};
+//!
+//! pool::init(AllocOptions::new(), 0x10000, Policy::Fail).unwrap();
+//!
+//! let key = UnswapArray::<u8, PoolImpl>::try_new_with(AllocOptions::new(), 0, 32).unwrap();
+//! assert!(pool::remaining() < pool::capacity());
+//! drop(key);
+//! assert_eq!(pool::remaining(), pool::capacity());
+//! ```
+use crate::impl_unix::UnixImpl;
+use crate::raw::wipe_bytes;
+use crate::{AllocOptions, DefaultImpl, Error, OsImpl, Protection};
+use std::alloc::Layout;
+use std::ffi::c_void;
+#[cfg(feature = "mock")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+/// Backend serving allocations from the pool reserved by [init]
+pub struct PoolImpl;
+
+/// What to do when the pool has no room for an allocation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Fail with [Error::PoolExhausted]
+    Fail,
+    /// Wait until enough memory is returned to the pool.
+    ///
+    /// Allocations which could never fit still fail with
+    /// [Error::PoolExhausted].
+    Block,
+    /// Allocate the pages directly from [DefaultImpl]
+    Fallback,
+}
+
+struct Pool {
+    base: usize,
+    size: usize,
+    /// Whether each page of the region is handed out
+    used: Vec<bool>,
+    free: usize,
+    policy: Policy,
+}
+
+static POOL: Mutex<Option<Pool>> = Mutex::new(None);
+/// Signalled whenever pages are returned to the pool
+static RETURNED: Condvar = Condvar::new();
+/// Number of protection changes left to fail, see [fail_mprotect]
+#[cfg(feature = "mock")]
+static FAIL_PROTECT: AtomicUsize = AtomicUsize::new(0);
+
+impl Pool {
+    fn contains(&self, at: usize) -> bool {
+        at >= self.base && at < self.base + self.size
+    }
+
+    /// Finds `count` free pages in a row, starting at an address
+    /// aligned to `align`
+    fn find(&self, count: usize, align: usize) -> Option<usize> {
+        self.find_in(&self.used, count, align)
+    }
+
+    /// Same as [Pool::find], but with the pages marked as in `used`
+    fn find_in(&self, used: &[bool], count: usize, align: usize) -> Option<usize> {
+        let page_size = UnixImpl::page_size();
+        let mut start = 0;
+        while start + count <= used.len() {
+            if (self.base + start * page_size) & (align - 1) != 0 {
+                start += 1;
+                continue;
+            }
+            match used[start..start + count].iter().rposition(|&used| used) {
+                Some(last_used) => start += last_used + 1,
+                None => return Some(start),
+            }
+        }
+        None
+    }
+
+    /// Returns `true` if `count` pages aligned to `align` would fit
+    /// once everything is returned to the pool
+    fn could_fit(&self, count: usize, align: usize) -> bool {
+        self.find_in(&vec![false; self.used.len()], count, align)
+            .is_some()
+    }
+
+    fn mark(&mut self, start: usize, count: usize, used: bool) {
+        for page in &mut self.used[start..start + count] {
+            *page = used;
+        }
+    }
+}
+
+fn lock() -> MutexGuard<'static, Option<Pool>> {
+    // Pages are only marked once nothing can fail anymore, so a
+    // panic elsewhere never leaves them half handed out
+    POOL.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Makes the next `count` protection changes of pool pages fail
+/// with `EACCES`, to test how they are recovered from
+#[cfg(feature = "mock")]
+pub fn fail_mprotect(count: usize) {
+    FAIL_PROTECT.store(count, Ordering::Relaxed);
+}
+
+unsafe fn protect(
+    at: *mut c_void,
+    layout: Layout,
+    options: &AllocOptions,
+    protection: Protection,
+) -> Result<(), Error> {
+    #[cfg(feature = "mock")]
+    {
+        let fail = FAIL_PROTECT.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+            count.checked_sub(1)
+        });
+        if fail.is_ok() {
+            return Err(Error::OsError {
+                call: "mprotect",
+                source: std::io::Error::from_raw_os_error(libc::EACCES),
+            });
+        }
+    }
+    UnixImpl::protect_pages(at, layout, options, protection)
+}
+
+/// Reserves and locks `size` bytes (rounded up to the page size)
+/// for the pool, using `policy` once it runs out.
+///
+/// # Panics
+///
+/// Panics if the pool has already been initialized.
+pub fn init(options: AllocOptions, size: usize, policy: Policy) -> Result<(), Error> {
+    let mut pool = lock();
+    assert!(pool.is_none(), "Pool already initialized");
+
+    let size = UnixImpl::page_align(size).ok_or(Error::LayoutError)?;
+    let pages = size / UnixImpl::page_size();
+    let layout =
+        Layout::from_size_align(size, UnixImpl::page_size()).map_err(|_| Error::LayoutError)?;
+    let base = match size {
+        0 => 0,
+        _ => DefaultImpl::alloc_pages(layout, &options)? as usize,
+    };
+
+    *pool = Some(Pool {
+        base,
+        size,
+        used: vec![false; pages],
+        free: size,
+        policy,
+    });
+    Ok(())
+}
+
+/// Changes what happens when the pool runs out.
+///
+/// # Panics
+///
+/// Panics if the pool has not been initialized.
+pub fn set_policy(policy: Policy) {
+    let mut pool = lock();
+    pool.as_mut().expect("Pool not initialized").policy = policy;
+    // Blocked allocations may have to fail or fall back now
+    RETURNED.notify_all();
+}
+
+/// Returns the size of the pool in bytes, zero if it has not been
+/// initialized
+pub fn capacity() -> usize {
+    lock().as_ref().map_or(0, |pool| pool.size)
+}
+
+/// Returns the number of bytes not handed out.
+///
+/// As the free pages may not be contiguous, an allocation of this
+/// size is not guaranteed to succeed.
+pub fn remaining() -> usize {
+    lock().as_ref().map_or(0, |pool| pool.free)
+}
+
+unsafe impl OsImpl for PoolImpl {
+    fn page_size() -> usize {
+        UnixImpl::page_size()
+    }
+
+    fn alloc_pages(layout: Layout, options: &AllocOptions) -> Result<*mut c_void, Error> {
+        if !Self::is_page_aligned(layout.size()) {
+            return Err(Error::AlignError);
+        }
+        let page_size = Self::page_size();
+        let count = layout.size() / page_size;
+        let align = layout.align().max(page_size);
+
+        let mut guard = lock();
+        loop {
+            let pool = match guard.as_mut() {
+                Some(pool) => pool,
+                None => return Err(Error::PoolExhausted),
+            };
+            if let Some(start) = pool.find(count, align) {
+                pool.mark(start, count, true);
+                pool.free -= layout.size();
+                return Ok((pool.base + start * page_size) as *mut c_void);
+            }
+            match pool.policy {
+                Policy::Fail => return Err(Error::PoolExhausted),
+                Policy::Fallback => {
+                    drop(guard);
+                    return DefaultImpl::alloc_pages(layout, options);
+                }
+                Policy::Block if !pool.could_fit(count, align) => return Err(Error::PoolExhausted),
+                Policy::Block => {
+                    guard = RETURNED
+                        .wait(guard)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                }
+            }
+        }
+    }
+
+    unsafe fn free_pages(at: *mut c_void, layout: Layout, options: &AllocOptions) {
+        let in_pool = lock()
+            .as_ref()
+            .is_some_and(|pool| pool.contains(at as usize));
+        if !in_pool {
+            DefaultImpl::free_pages(at, layout, options);
+            return;
+        }
+
+        // Unlike unmapped pages, these are handed out again as they
+        // are, so they may still be sealed or hold secrets. If they
+        // cannot be opened up, they are never reused.
+        if protect(at, layout, options, Protection::ReadWrite).is_err() {
+            return;
+        }
+        wipe_bytes(at as *mut u8, layout.size());
+
+        let mut guard = lock();
+        let pool = guard.as_mut().unwrap();
+        let start = (at as usize - pool.base) / Self::page_size();
+        pool.mark(start, layout.size() / Self::page_size(), false);
+        pool.free += layout.size();
+        RETURNED.notify_all();
+    }
+
+    unsafe fn protect_pages(
+        at: *mut c_void,
+        layout: Layout,
+        options: &AllocOptions,
+        protection: Protection,
+    ) -> Result<(), Error> {
+        protect(at, layout, options, protection)
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{AllocOptions, Error, OsImpl, Protection};
+use std::alloc::Layout;
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::ptr;
+use std::sync::atomic::{self, Ordering};
+
+/// Locked region allocated from the `A` backend, which knows
+/// nothing about its contents.
+///
+/// The region is wiped and released when dropped.
+pub(crate) struct RawPages<A: OsImpl> {
+    data: *mut c_void,
+    layout: Layout,
+    options: AllocOptions,
+    _pd: PhantomData<A>,
+}
+
+/// Overwrites `size` bytes at `at` with zeroes.
+///
+/// The writes are volatile and followed by a compiler fence, so
+/// they cannot be elided even if the memory is never read again.
+///
+/// # Safety
+///
+/// `at` must be valid for writes of `size` bytes.
+pub(crate) unsafe fn wipe_bytes(at: *mut u8, size: usize) {
+    for i in 0..size {
+        ptr::write_volatile(at.add(i), 0);
+    }
+    atomic::compiler_fence(Ordering::SeqCst);
+}
+
+impl<A: OsImpl> RawPages<A> {
+    /// Returns an empty region aligned to `align`, which never
+    /// touches the OS
+    pub(crate) fn dangling(options: AllocOptions, align: usize) -> Self {
+        Self {
+            data: align as *mut c_void,
+            layout: Layout::from_size_align(0, align).unwrap(),
+            options,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Allocates a region fitting `layout`, with its size rounded
+    /// up to the page boundary
+    pub(crate) fn try_alloc(options: AllocOptions, layout: Layout) -> Result<Self, Error> {
+        let size = A::page_align(layout.size()).ok_or(Error::LayoutError)?;
+        if size == 0 {
+            // Empty arrays and arrays of zero-sized types never
+            // touch the OS, so just use an aligned dangling pointer
+            return Ok(Self::dangling(options, layout.align()));
+        }
+        let layout =
+            Layout::from_size_align(size, layout.align()).map_err(|_| Error::LayoutError)?;
+        let data = A::alloc_pages(layout, &options)?;
+
+        Ok(Self {
+            data,
+            layout,
+            options,
+            _pd: PhantomData,
+        })
+    }
+
+    /// Allocates a region for `len` elements of type `T`
+    pub(crate) fn try_alloc_array<T>(options: AllocOptions, len: usize) -> Result<Self, Error> {
+        let layout = Layout::array::<T>(len).map_err(|_| Error::LayoutError)?;
+        Self::try_alloc(options, layout)
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut c_void {
+        self.data
+    }
+
+    /// Returns the number of `T` elements fitting in the region
+    pub(crate) fn capacity<T>(&self) -> usize {
+        match std::mem::size_of::<T>() {
+            0 => usize::MAX,
+            size => self.layout.size() / size,
+        }
+    }
+
+    pub(crate) fn options(&self) -> &AllocOptions {
+        &self.options
+    }
+
+    /// Overwrites the whole region with zeroes
+    pub(crate) fn wipe(&mut self) {
+        unsafe {
+            wipe_bytes(self.data as *mut u8, self.layout.size());
+        }
+    }
+
+    /// Makes room for at least `capacity` elements of type `T`,
+    /// keeping the first `len` of them.
+    ///
+    /// The region is grown in place if the backend allows it,
+    /// otherwise the elements are moved to a new region and the
+    /// old one is wiped and released. On failure the region is
+    /// left unchanged.
+    pub(crate) fn try_grow<T>(&mut self, len: usize, capacity: usize) -> Result<(), Error> {
+        if capacity <= self.capacity::<T>() {
+            return Ok(());
+        }
+        let layout = Layout::array::<T>(capacity).map_err(|_| Error::LayoutError)?;
+        let size = A::page_align(layout.size()).ok_or(Error::LayoutError)?;
+        if self.layout.size() != 0 && self.try_resize_in_place(size) {
+            return Ok(());
+        }
+
+        let pages = Self::try_alloc(self.options, layout)?;
+        unsafe {
+            ptr::copy_nonoverlapping(self.data as *const T, pages.data as *mut T, len);
+        }
+        // The old region is wiped and released here
+        *self = pages;
+
+        Ok(())
+    }
+
+    /// Releases the pages not needed for `len` elements of type
+    /// `T`, wiping them first.
+    ///
+    /// The region is shrunk in place if the backend allows it,
+    /// otherwise the elements are moved to a new, smaller region.
+    /// If that cannot be allocated, the region is kept as is.
+    pub(crate) fn shrink_to<T>(&mut self, len: usize) {
+        let size = match A::page_align(len * std::mem::size_of::<T>()) {
+            Some(size) if size < self.layout.size() => size,
+            _ => return,
+        };
+        unsafe {
+            wipe_bytes((self.data as *mut u8).add(size), self.layout.size() - size);
+        }
+        if size == 0 {
+            *self = Self::dangling(self.options, self.layout.align());
+            return;
+        }
+        if self.try_resize_in_place(size) {
+            return;
+        }
+
+        let layout = Layout::from_size_align(size, self.layout.align()).unwrap();
+        if let Ok(pages) = Self::try_alloc(self.options, layout) {
+            unsafe {
+                ptr::copy_nonoverlapping(self.data as *const T, pages.data as *mut T, len);
+            }
+            *self = pages;
+        }
+    }
+
+    /// Resizes a non-empty region to `size` bytes without moving it
+    fn try_resize_in_place(&mut self, size: usize) -> bool {
+        let resized = unsafe { A::resize_pages(self.data, self.layout, &self.options, size) };
+        if resized {
+            self.layout = Layout::from_size_align(size, self.layout.align()).unwrap();
+        }
+        resized
+    }
+
+    /// Releases the region without wiping it first, for when it
+    /// cannot be accessed anymore.
+    ///
+    /// # Safety
+    ///
+    /// The region must not be used afterwards.
+    pub(crate) unsafe fn free_unwiped(self) {
+        let this = std::mem::ManuallyDrop::new(self);
+        if this.layout.size() != 0 {
+            A::free_pages(this.data, this.layout, &this.options);
+        }
+    }
+
+    /// Changes the access permissions of the region.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure no access to the region is made
+    /// which the new protection forbids.
+    pub(crate) unsafe fn protect(&self, protection: Protection) -> Result<(), Error> {
+        if self.layout.size() == 0 {
+            return Ok(());
+        }
+        A::protect_pages(self.data, self.layout, &self.options, protection)
+    }
+}
+
+impl<A: OsImpl> Drop for RawPages<A> {
+    fn drop(&mut self) {
+        if self.layout.size() == 0 {
+            return;
+        }
+        self.wipe();
+        unsafe {
+            A::free_pages(self.data, self.layout, &self.options);
+        }
+    }
+}
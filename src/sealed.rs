@@ -0,0 +1,168 @@
+use crate::{DefaultImpl, Error, OsImpl, Protection, UnswapArray};
+use std::cell::Cell;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+
+/// [UnswapArray] whose pages are inaccessible (or read-only)
+/// while not in use.
+///
+/// The contents can only be reached through [ReadGuard] and
+/// [WriteGuard], which open the pages up for their lifetime and
+/// seal them again when the last guard is dropped.
+///
+/// Guards cannot report errors when dropped, so if sealing the
+/// pages again fails, they are left open until the next guard
+/// is dropped, see [SealedArray::is_sealed].
+pub struct SealedArray<T, A: OsImpl = DefaultImpl> {
+    array: ManuallyDrop<UnswapArray<T, A>>,
+    rest: Protection,
+    /// Protection the pages actually have right now
+    current: Cell<Protection>,
+    readers: Cell<usize>,
+}
+
+/// Shared access to the contents of a [SealedArray]
+pub struct ReadGuard<'a, T, A: OsImpl = DefaultImpl> {
+    sealed: &'a SealedArray<T, A>,
+}
+
+/// Exclusive access to the contents of a [SealedArray]
+pub struct WriteGuard<'a, T, A: OsImpl = DefaultImpl> {
+    sealed: &'a mut SealedArray<T, A>,
+}
+
+impl<T, A: OsImpl> UnswapArray<T, A> {
+    /// Seals the array pages with `rest` protection, which is kept
+    /// while no access guards exist.
+    ///
+    /// If the protection cannot be changed, the array is wiped
+    /// and released.
+    pub fn seal(self, rest: Protection) -> Result<SealedArray<T, A>, Error> {
+        let mut sealed = SealedArray {
+            array: ManuallyDrop::new(self),
+            rest: Protection::ReadWrite,
+            current: Cell::new(Protection::ReadWrite),
+            readers: Cell::new(0),
+        };
+        sealed.protect(rest)?;
+        sealed.rest = rest;
+
+        Ok(sealed)
+    }
+}
+
+impl<T, A: OsImpl> SealedArray<T, A> {
+    fn protect(&self, protection: Protection) -> Result<(), Error> {
+        if self.current.get() != protection {
+            unsafe { self.array.pages.protect(protection)? };
+            self.current.set(protection);
+        }
+        Ok(())
+    }
+
+    /// Returns the protection the pages have while not in use
+    pub fn rest_protection(&self) -> Protection {
+        self.rest
+    }
+
+    /// Returns `true` if the pages currently have their rest
+    /// protection.
+    ///
+    /// This is `false` while guards exist, and also if sealing the
+    /// pages again failed when the last guard was dropped.
+    pub fn is_sealed(&self) -> bool {
+        self.current.get() == self.rest
+    }
+
+    /// Opens the pages for reading until the guard is dropped.
+    ///
+    /// Read guards may be nested, the pages are sealed again once
+    /// the last one is gone.
+    pub fn read(&self) -> Result<ReadGuard<'_, T, A>, Error> {
+        if self.current.get() == Protection::NoAccess {
+            self.protect(Protection::ReadOnly)?;
+        }
+        self.readers.set(self.readers.get() + 1);
+
+        Ok(ReadGuard { sealed: self })
+    }
+
+    /// Opens the pages for reading and writing until the guard
+    /// is dropped.
+    pub fn write(&mut self) -> Result<WriteGuard<'_, T, A>, Error> {
+        self.protect(Protection::ReadWrite)?;
+
+        Ok(WriteGuard { sealed: self })
+    }
+
+    /// Opens the pages up permanently and returns the array
+    pub fn unseal(self) -> Result<UnswapArray<T, A>, Error> {
+        self.protect(Protection::ReadWrite)?;
+        let mut sealed = ManuallyDrop::new(self);
+
+        Ok(unsafe { ManuallyDrop::take(&mut sealed.array) })
+    }
+
+    /// Seals the pages again once no guards are left. A failure
+    /// is only recorded, see [SealedArray::is_sealed].
+    fn reseal(&self) {
+        let _ = self.protect(self.rest);
+    }
+}
+
+impl<T, A: OsImpl> Drop for SealedArray<T, A> {
+    fn drop(&mut self) {
+        // The pages have to be writable to be wiped
+        if self.protect(Protection::ReadWrite).is_ok() {
+            unsafe {
+                ManuallyDrop::drop(&mut self.array);
+            }
+            return;
+        }
+        // Neither the elements nor the pages can be touched, so
+        // just release them. Unmapped pages are cleared by the
+        // kernel, backends reusing them wipe them on their own.
+        unsafe {
+            let array = ManuallyDrop::take(&mut self.array);
+            array.into_raw_pages().free_unwiped();
+        }
+    }
+}
+
+impl<T, A: OsImpl> Deref for ReadGuard<'_, T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.sealed.array
+    }
+}
+
+impl<T, A: OsImpl> Drop for ReadGuard<'_, T, A> {
+    fn drop(&mut self) {
+        let readers = self.sealed.readers.get() - 1;
+        self.sealed.readers.set(readers);
+        if readers == 0 {
+            self.sealed.reseal();
+        }
+    }
+}
+
+impl<T, A: OsImpl> Deref for WriteGuard<'_, T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.sealed.array
+    }
+}
+
+impl<T, A: OsImpl> DerefMut for WriteGuard<'_, T, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.sealed.array
+    }
+}
+
+impl<T, A: OsImpl> Drop for WriteGuard<'_, T, A> {
+    fn drop(&mut self) {
+        self.sealed.reseal();
+    }
+}
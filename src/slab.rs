@@ -0,0 +1,365 @@
+use crate::raw::wipe_bytes;
+use crate::{AllocOptions, DefaultImpl, Error, OsImpl};
+use std::alloc::Layout;
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+use std::sync::{Mutex, MutexGuard};
+
+/// Smallest slot size, enough to hold a free list link
+const MIN_SLOT: usize = 16;
+/// Number of size classes, from 16 bytes up to 512 KiB. Only the
+/// classes up to a quarter of the page size are used.
+const CLASS_COUNT: usize = 16;
+
+/// Allocator packing small objects into shared locked pages
+///
+/// Requests are rounded up to a power-of-two size class, and each
+/// class carves its slots out of whole pages allocated from the `A`
+/// backend. Slots are wiped as soon as they are freed, and a page
+/// is wiped and returned to the OS once its last slot is freed.
+///
+/// Requests larger than a quarter of a page get pages of their
+/// own, just like the rest of the crate.
+///
+/// ```
+/// use unswap::{AllocOptions, Slab, SlabBox};
+///
+/// static KEYS: Slab = Slab::new(AllocOptions::new());
+///
+/// let keys: Vec<_> = (0..64u8)
+///     .map(|i| SlabBox::new_in(&KEYS, [i; 32]))
+///     .collect();
+/// // All 64 keys share a couple of pages
+/// assert!(KEYS.page_count() <= 2);
+/// drop(keys);
+/// assert_eq!(KEYS.page_count(), 0);
+/// ```
+pub struct Slab<A: OsImpl = DefaultImpl> {
+    options: AllocOptions,
+    classes: Mutex<Classes>,
+    _pd: PhantomData<fn() -> A>,
+}
+
+/// Value allocated from a [Slab]
+pub struct SlabBox<'a, T, A: OsImpl = DefaultImpl> {
+    slab: &'a Slab<A>,
+    value: NonNull<T>,
+}
+
+/// Kept at the start of each slab page, the page is found from
+/// a slot address by rounding it down to the page boundary
+struct PageHeader {
+    next: *mut PageHeader,
+    prev: *mut PageHeader,
+    free: *mut FreeSlot,
+    used: usize,
+}
+
+struct FreeSlot {
+    next: *mut FreeSlot,
+}
+
+/// Page lists of a single size class. The pages with free slots
+/// are kept apart from the full ones, so allocation never has to
+/// search.
+#[derive(Clone, Copy)]
+struct Class {
+    partial: *mut PageHeader,
+    full: *mut PageHeader,
+}
+
+struct Classes {
+    lists: [Class; CLASS_COUNT],
+    pages: usize,
+}
+
+// The page lists are only ever touched with the mutex held
+unsafe impl Send for Classes {}
+
+unsafe impl<T: Send, A: OsImpl> Send for SlabBox<'_, T, A> {}
+unsafe impl<T: Sync, A: OsImpl> Sync for SlabBox<'_, T, A> {}
+
+impl Class {
+    const EMPTY: Self = Self {
+        partial: ptr::null_mut(),
+        full: ptr::null_mut(),
+    };
+}
+
+unsafe fn push(list: &mut *mut PageHeader, page: *mut PageHeader) {
+    (*page).prev = ptr::null_mut();
+    (*page).next = *list;
+    if !list.is_null() {
+        (**list).prev = page;
+    }
+    *list = page;
+}
+
+unsafe fn unlink(list: &mut *mut PageHeader, page: *mut PageHeader) {
+    if (*page).prev.is_null() {
+        *list = (*page).next;
+    } else {
+        (*(*page).prev).next = (*page).next;
+    }
+    if !(*page).next.is_null() {
+        (*(*page).next).prev = (*page).prev;
+    }
+}
+
+impl<A: OsImpl> Slab<A> {
+    /// Creates an empty slab, which allocates pages from the `A`
+    /// backend with the given `options` as needed
+    pub const fn new(options: AllocOptions) -> Self {
+        Self {
+            options,
+            classes: Mutex::new(Classes {
+                lists: [Class::EMPTY; CLASS_COUNT],
+                pages: 0,
+            }),
+            _pd: PhantomData,
+        }
+    }
+
+    /// Returns the options the pages are allocated with
+    pub fn options(&self) -> &AllocOptions {
+        &self.options
+    }
+
+    /// Returns the number of slab pages currently allocated, not
+    /// counting the requests too large for the size classes
+    pub fn page_count(&self) -> usize {
+        self.lock().pages
+    }
+
+    /// Allocates a block fitting `layout`.
+    ///
+    /// The block is locked in memory, but its contents are
+    /// unspecified.
+    pub fn alloc(&self, layout: Layout) -> Result<NonNull<u8>, Error> {
+        match Self::class(layout) {
+            Some(class) => self.alloc_slot(class),
+            None => {
+                let layout = Self::large_layout(layout)?;
+                let at = A::alloc_pages(layout, &self.options)?;
+                Ok(NonNull::new(at as *mut u8).unwrap())
+            }
+        }
+    }
+
+    /// Wipes and releases a block.
+    ///
+    /// # Safety
+    ///
+    /// `at` must have been returned by [Slab::alloc] of this slab
+    /// called with the same `layout`, and must not be used
+    /// afterwards.
+    pub unsafe fn free(&self, at: NonNull<u8>, layout: Layout) {
+        match Self::class(layout) {
+            Some(class) => self.free_slot(at, class),
+            None => {
+                let layout = Self::large_layout(layout).unwrap();
+                wipe_bytes(at.as_ptr(), layout.size());
+                A::free_pages(at.as_ptr() as *mut c_void, layout, &self.options);
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Classes> {
+        // The lists are consistent between the calls even if some
+        // thread panicked
+        self.classes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the size class index for `layout`, or `None` if it
+    /// needs pages of its own
+    fn class(layout: Layout) -> Option<usize> {
+        let slot = layout
+            .size()
+            .max(layout.align())
+            .max(MIN_SLOT)
+            .checked_next_power_of_two()?;
+        let index = (slot / MIN_SLOT).trailing_zeros() as usize;
+        if slot > A::page_size() / 4 || index >= CLASS_COUNT {
+            return None;
+        }
+        Some(index)
+    }
+
+    fn large_layout(layout: Layout) -> Result<Layout, Error> {
+        let size = A::page_align(layout.size()).ok_or(Error::LayoutError)?;
+        Layout::from_size_align(size, layout.align()).map_err(|_| Error::LayoutError)
+    }
+
+    fn page_layout() -> Layout {
+        let page_size = A::page_size();
+        Layout::from_size_align(page_size, page_size).unwrap()
+    }
+
+    fn alloc_slot(&self, class: usize) -> Result<NonNull<u8>, Error> {
+        let mut classes = self.lock();
+        let classes = &mut *classes;
+        let list = &mut classes.lists[class];
+
+        unsafe {
+            if list.partial.is_null() {
+                let page = self.alloc_page(class)?;
+                push(&mut list.partial, page);
+                classes.pages += 1;
+            }
+
+            let page = list.partial;
+            let slot = (*page).free;
+            (*page).free = (*slot).next;
+            (*page).used += 1;
+            if (*page).free.is_null() {
+                unlink(&mut list.partial, page);
+                push(&mut list.full, page);
+            }
+            // Do not leak the free list link to the caller
+            (*slot).next = ptr::null_mut();
+
+            Ok(NonNull::new(slot as *mut u8).unwrap())
+        }
+    }
+
+    /// Allocates a page and threads the free list through all of
+    /// its slots
+    fn alloc_page(&self, class: usize) -> Result<*mut PageHeader, Error> {
+        let layout = Self::page_layout();
+        let page = A::alloc_pages(layout, &self.options)? as *mut PageHeader;
+        let slot_size = MIN_SLOT << class;
+        let first = mem::size_of::<PageHeader>().div_ceil(slot_size);
+        let count = layout.size() / slot_size;
+
+        unsafe {
+            let mut free = ptr::null_mut();
+            for i in (first..count).rev() {
+                let slot = (page as *mut u8).add(i * slot_size) as *mut FreeSlot;
+                (*slot).next = free;
+                free = slot;
+            }
+            page.write(PageHeader {
+                next: ptr::null_mut(),
+                prev: ptr::null_mut(),
+                free,
+                used: 0,
+            });
+        }
+
+        Ok(page)
+    }
+
+    unsafe fn free_slot(&self, at: NonNull<u8>, class: usize) {
+        let slot_size = MIN_SLOT << class;
+        let layout = Self::page_layout();
+        let page = (at.as_ptr() as usize & !(layout.size() - 1)) as *mut PageHeader;
+
+        wipe_bytes(at.as_ptr(), slot_size);
+
+        let mut classes = self.lock();
+        let classes = &mut *classes;
+        let list = &mut classes.lists[class];
+
+        if (*page).free.is_null() {
+            unlink(&mut list.full, page);
+            push(&mut list.partial, page);
+        }
+        (*page).used -= 1;
+        if (*page).used == 0 {
+            unlink(&mut list.partial, page);
+            classes.pages -= 1;
+            wipe_bytes(page as *mut u8, layout.size());
+            A::free_pages(page as *mut c_void, layout, &self.options);
+            return;
+        }
+
+        let slot = at.as_ptr() as *mut FreeSlot;
+        (*slot).next = (*page).free;
+        (*page).free = slot;
+    }
+}
+
+impl<A: OsImpl> Drop for Slab<A> {
+    fn drop(&mut self) {
+        // Blocks which were never freed are released along with
+        // the slab
+        let layout = Self::page_layout();
+        let classes = self.lock();
+        for list in classes.lists.iter() {
+            for &head in [list.partial, list.full].iter() {
+                let mut page = head;
+                while !page.is_null() {
+                    unsafe {
+                        let next = (*page).next;
+                        wipe_bytes(page as *mut u8, layout.size());
+                        A::free_pages(page as *mut c_void, layout, &self.options);
+                        page = next;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T, A: OsImpl> SlabBox<'a, T, A> {
+    /// Moves `value` into a slot of `slab`
+    pub fn try_new_in(slab: &'a Slab<A>, value: T) -> Result<Self, Error> {
+        let value_ptr = slab.alloc(Layout::new::<T>())?.cast::<T>();
+        unsafe {
+            value_ptr.as_ptr().write(value);
+        }
+
+        Ok(Self {
+            slab,
+            value: value_ptr,
+        })
+    }
+
+    /// Moves `value` into a slot of `slab`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slab could not allocate a page.
+    pub fn new_in(slab: &'a Slab<A>, value: T) -> Self {
+        Self::try_new_in(slab, value).expect("Failed to allocate locked memory pages")
+    }
+}
+
+impl<T, A: OsImpl> Drop for SlabBox<'_, T, A> {
+    fn drop(&mut self) {
+        struct Free<'s, 'a, T, A: OsImpl>(&'s mut SlabBox<'a, T, A>);
+
+        // The slot is wiped and freed even if the destructor panics
+        impl<T, A: OsImpl> Drop for Free<'_, '_, T, A> {
+            fn drop(&mut self) {
+                unsafe {
+                    self.0.slab.free(self.0.value.cast(), Layout::new::<T>());
+                }
+            }
+        }
+
+        let free = Free(self);
+        unsafe {
+            ptr::drop_in_place(free.0.value.as_ptr());
+        }
+    }
+}
+
+impl<T, A: OsImpl> Deref for SlabBox<'_, T, A> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T, A: OsImpl> DerefMut for SlabBox<'_, T, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.value.as_mut() }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{AllocOptions, DefaultImpl, Error, OsImpl, UnswapVec};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::str::{self, Utf8Error};
+
+/// UTF-8 string residing in non-swappable memory
+///
+/// Backed by an [UnswapVec], so growing the string never copies
+/// its contents through swappable memory.
+pub struct UnswapString<A: OsImpl = DefaultImpl> {
+    vec: UnswapVec<u8, A>,
+}
+
+/// Error returned when converting bytes which are not valid
+/// UTF-8 into an [UnswapString]
+///
+/// The bytes can be taken back with [FromUtf8Error::into_bytes].
+pub struct FromUtf8Error<A: OsImpl = DefaultImpl> {
+    bytes: UnswapVec<u8, A>,
+    error: Utf8Error,
+}
+
+impl<A: OsImpl> UnswapString<A> {
+    /// Creates an empty string, which allocates pages from the
+    /// `A` backend with the given `options` once text is added.
+    pub fn new_with(options: AllocOptions) -> Self {
+        Self {
+            vec: UnswapVec::new_with(options),
+        }
+    }
+
+    /// Converts a vector of bytes to a string, checking that the
+    /// bytes are valid UTF-8.
+    ///
+    /// The bytes are not copied.
+    pub fn from_utf8(bytes: UnswapVec<u8, A>) -> Result<Self, FromUtf8Error<A>> {
+        match str::from_utf8(&bytes) {
+            Ok(_) => Ok(Self { vec: bytes }),
+            Err(error) => Err(FromUtf8Error { bytes, error }),
+        }
+    }
+
+    /// Same as [UnswapString::try_from_utf8_slice], but allocates
+    /// the pages from the `A` backend with the given `options`.
+    pub fn try_from_utf8_slice_with(options: AllocOptions, bytes: &[u8]) -> Result<Self, Error> {
+        let text = str::from_utf8(bytes).map_err(Error::Utf8Error)?;
+        Self::try_from_str_with(options, text)
+    }
+
+    /// Same as [UnswapString::try_from_str], but allocates the
+    /// pages from the `A` backend with the given `options`.
+    pub fn try_from_str_with(options: AllocOptions, text: &str) -> Result<Self, Error> {
+        let mut string = Self::new_with(options);
+        string.try_push_str(text)?;
+        Ok(string)
+    }
+
+    /// Returns the string contents
+    pub fn as_str(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(&self.vec) }
+    }
+
+    /// Returns the string contents
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe { str::from_utf8_unchecked_mut(&mut self.vec) }
+    }
+
+    /// Returns the string contents as bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.vec
+    }
+
+    /// Returns the number of bytes the string can hold without
+    /// reallocating
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    /// Makes sure there is room for at least `additional` more
+    /// bytes
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), Error> {
+        self.vec.try_reserve(additional)
+    }
+
+    /// Appends `text` to the end of the string.
+    ///
+    /// On failure the string is left unchanged.
+    pub fn try_push_str(&mut self, text: &str) -> Result<(), Error> {
+        self.vec.try_extend_from_slice(text.as_bytes())
+    }
+
+    /// Appends `text` to the end of the string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string could not grow.
+    pub fn push_str(&mut self, text: &str) {
+        self.vec.extend_from_slice(text.as_bytes())
+    }
+
+    /// Appends a character to the end of the string.
+    ///
+    /// On failure the string is left unchanged.
+    pub fn try_push(&mut self, ch: char) -> Result<(), Error> {
+        self.try_push_str(ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Appends a character to the end of the string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string could not grow.
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Removes the last character and returns it
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.as_str().chars().next_back()?;
+        self.vec.truncate(self.vec.len() - ch.len_utf8());
+        Some(ch)
+    }
+
+    /// Shortens the string to `len` bytes.
+    ///
+    /// Does nothing if `len` is not less than the current length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` does not lie on a character boundary.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            assert!(
+                self.is_char_boundary(len),
+                "Truncating not on a char boundary"
+            );
+            self.vec.truncate(len);
+        }
+    }
+
+    /// Empties the string, keeping the capacity
+    pub fn clear(&mut self) {
+        self.vec.clear();
+    }
+
+    /// Overwrites the whole underlying memory region with zeroes,
+    /// leaving the string empty.
+    pub fn wipe(&mut self) {
+        self.vec.wipe();
+    }
+
+    /// Converts the string into a vector of its bytes, without
+    /// copying
+    pub fn into_bytes(self) -> UnswapVec<u8, A> {
+        self.vec
+    }
+}
+
+impl UnswapString {
+    /// Creates an empty string.
+    ///
+    /// No pages are allocated until text is added.
+    pub fn new() -> Self {
+        Self::new_with(AllocOptions::new())
+    }
+
+    /// Copies `bytes` into a new string, checking that they are
+    /// valid UTF-8
+    pub fn try_from_utf8_slice(bytes: &[u8]) -> Result<Self, Error> {
+        Self::try_from_utf8_slice_with(AllocOptions::new(), bytes)
+    }
+
+    /// Copies `text` into a new string
+    pub fn try_from_str(text: &str) -> Result<Self, Error> {
+        Self::try_from_str_with(AllocOptions::new(), text)
+    }
+}
+
+impl Default for UnswapString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&str> for UnswapString {
+    /// Copies `text` into a new string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked.
+    fn from(text: &str) -> Self {
+        Self::try_from_str(text).expect("Failed to allocate locked memory pages")
+    }
+}
+
+impl<A: OsImpl> Deref for UnswapString<A> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<A: OsImpl> DerefMut for UnswapString<A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_str()
+    }
+}
+
+impl<A: OsImpl> AsRef<str> for UnswapString<A> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<A: OsImpl> AsRef<[u8]> for UnswapString<A> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<A: OsImpl> fmt::Write for UnswapString<A> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.try_push_str(text).map_err(|_| fmt::Error)
+    }
+}
+
+impl<A: OsImpl> FromUtf8Error<A> {
+    /// Returns the bytes which failed the conversion
+    pub fn into_bytes(self) -> UnswapVec<u8, A> {
+        self.bytes
+    }
+
+    /// Returns details about the conversion failure
+    pub fn utf8_error(&self) -> Utf8Error {
+        self.error
+    }
+}
+
+// The bytes are secret, so only the error itself is printed
+impl<A: OsImpl> fmt::Debug for FromUtf8Error<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FromUtf8Error")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A: OsImpl> fmt::Display for FromUtf8Error<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl<A: OsImpl> std::error::Error for FromUtf8Error<A> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,280 @@
+use crate::raw::{self, RawPages};
+use crate::{AllocOptions, DefaultImpl, Error, OsImpl};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::slice;
+
+/// Growable array residing in non-swappable memory
+///
+/// When the vector grows, the locked region is extended in place
+/// where the backend allows it. Otherwise a new locked region is
+/// allocated and the elements are moved there, while the old
+/// region is wiped and released. The contents never pass through
+/// swappable memory.
+pub struct UnswapVec<T, A: OsImpl = DefaultImpl> {
+    pages: RawPages<A>,
+    len: usize,
+    _pd: PhantomData<T>,
+}
+
+unsafe impl<T: Send, A: OsImpl> Send for UnswapVec<T, A> {}
+unsafe impl<T: Sync, A: OsImpl> Sync for UnswapVec<T, A> {}
+
+impl<T, A: OsImpl> UnswapVec<T, A> {
+    /// Creates an empty vector, which allocates pages from the
+    /// `A` backend with the given `options` once elements are
+    /// added.
+    pub fn new_with(options: AllocOptions) -> Self {
+        Self {
+            pages: RawPages::dangling(options, mem::align_of::<T>()),
+            len: 0,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Same as [UnswapVec::try_with_capacity], but allocates the
+    /// pages from the `A` backend with the given `options`.
+    pub fn try_with_capacity_with(options: AllocOptions, capacity: usize) -> Result<Self, Error> {
+        Ok(Self {
+            pages: RawPages::try_alloc_array::<T>(options, capacity)?,
+            len: 0,
+            _pd: PhantomData,
+        })
+    }
+
+    /// Returns the number of elements the vector can hold without
+    /// reallocating.
+    ///
+    /// As the pages are allocated whole, this is usually more than
+    /// was requested.
+    pub fn capacity(&self) -> usize {
+        self.pages.capacity::<T>()
+    }
+
+    /// Returns the options the vector pages are allocated with
+    pub fn options(&self) -> &AllocOptions {
+        self.pages.options()
+    }
+
+    /// Makes sure there is room for at least `additional` more
+    /// elements.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), Error> {
+        let required = self.len.checked_add(additional).ok_or(Error::LayoutError)?;
+        if required <= self.capacity() {
+            return Ok(());
+        }
+        // Grow geometrically, so pushing one by one does not
+        // reallocate on every page boundary
+        let capacity = required.max(self.capacity().saturating_mul(2));
+        self.pages.try_grow::<T>(self.len, capacity)
+    }
+
+    /// Makes sure there is room for at least `additional` more
+    /// elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked.
+    pub fn reserve(&mut self, additional: usize) {
+        self.try_reserve(additional)
+            .expect("Failed to allocate locked memory pages")
+    }
+
+    /// Appends an element to the back of the vector.
+    ///
+    /// If the vector could not grow, `value` is returned back along
+    /// with the error.
+    pub fn try_push(&mut self, value: T) -> Result<(), (T, Error)> {
+        if let Err(error) = self.try_reserve(1) {
+            return Err((value, error));
+        }
+        unsafe {
+            ptr::write((self.pages.as_ptr() as *mut T).add(self.len), value);
+        }
+        self.len += 1;
+
+        Ok(())
+    }
+
+    /// Appends an element to the back of the vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector could not grow.
+    pub fn push(&mut self, value: T) {
+        self.reserve(1);
+        unsafe {
+            ptr::write((self.pages.as_ptr() as *mut T).add(self.len), value);
+        }
+        self.len += 1;
+    }
+
+    /// Removes the last element and returns it
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe {
+            let slot = (self.pages.as_ptr() as *mut T).add(self.len);
+            let value = ptr::read(slot);
+            raw::wipe_bytes(slot as *mut u8, mem::size_of::<T>());
+            Some(value)
+        }
+    }
+
+    /// Drops and wipes the elements past `len`, keeping the
+    /// capacity.
+    ///
+    /// Does nothing if `len` is not less than the current length.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let size = (self.len - len) * mem::size_of::<T>();
+        let tail: *mut [T] = &mut self[len..];
+        // Set the length first so a panicking destructor cannot
+        // cause a double drop
+        self.len = len;
+        unsafe {
+            ptr::drop_in_place(tail);
+            raw::wipe_bytes(tail as *mut u8, size);
+        }
+    }
+
+    /// Drops and wipes all the elements, keeping the capacity
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Drops all the elements and overwrites the whole underlying
+    /// memory region with zeroes, leaving the vector empty.
+    pub fn wipe(&mut self) {
+        self.clear();
+        self.pages.wipe();
+    }
+
+    /// Takes the pages and the length apart, leaving the elements
+    /// to the caller
+    pub(crate) fn into_raw_parts(self) -> (RawPages<A>, usize) {
+        let this = mem::ManuallyDrop::new(self);
+        (unsafe { ptr::read(&this.pages) }, this.len)
+    }
+}
+
+impl<T> FromIterator<T> for UnswapVec<T> {
+    /// Collects the elements into locked memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector could not grow.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<T: Clone, A: OsImpl> UnswapVec<T, A> {
+    /// Appends clones of all the elements in `other`.
+    ///
+    /// On failure the vector is left unchanged.
+    pub fn try_extend_from_slice(&mut self, other: &[T]) -> Result<(), Error> {
+        self.try_reserve(other.len())?;
+        for value in other {
+            // The room has been reserved above
+            unsafe {
+                ptr::write((self.pages.as_ptr() as *mut T).add(self.len), value.clone());
+            }
+            self.len += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Appends clones of all the elements in `other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector could not grow.
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        self.try_extend_from_slice(other)
+            .expect("Failed to allocate locked memory pages")
+    }
+}
+
+impl<T> UnswapVec<T> {
+    /// Creates an empty vector.
+    ///
+    /// No pages are allocated until elements are added.
+    pub fn new() -> Self {
+        Self::new_with(AllocOptions::new())
+    }
+
+    /// Creates an empty vector with room for at least `capacity`
+    /// elements
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, Error> {
+        Self::try_with_capacity_with(AllocOptions::new(), capacity)
+    }
+
+    /// Creates an empty vector with room for at least `capacity`
+    /// elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pages could not be allocated or locked.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::try_with_capacity(capacity).expect("Failed to allocate locked memory pages")
+    }
+}
+
+impl<T> Default for UnswapVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, A: OsImpl> Extend<T> for UnswapVec<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a, T: Copy + 'a, A: OsImpl> Extend<&'a T> for UnswapVec<T, A> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
+impl<T, A: OsImpl> Drop for UnswapVec<T, A> {
+    fn drop(&mut self) {
+        // The pages are wiped and released afterwards even if one
+        // of the element destructors panics
+        let elements: *mut [T] = &mut **self;
+        unsafe {
+            ptr::drop_in_place(elements);
+        }
+    }
+}
+
+impl<T, A: OsImpl> Deref for UnswapVec<T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { slice::from_raw_parts(self.pages.as_ptr() as *const T, self.len) }
+    }
+}
+
+impl<T, A: OsImpl> DerefMut for UnswapVec<T, A> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { slice::from_raw_parts_mut(self.pages.as_ptr() as *mut T, self.len) }
+    }
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "mock")]
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::slice;
+use unswap::mock::{self, Call, MockImpl};
+use unswap::{AllocOptions, UnswapAlloc};
+
+type MockAlloc = UnswapAlloc<MockImpl>;
+
+/// Checks that every page released so far was wiped first
+fn assert_frees_wiped() {
+    for call in mock::calls() {
+        if let Call::Free { wiped, .. } = call {
+            assert!(wiped);
+        }
+    }
+}
+
+#[test]
+fn global_alloc() {
+    mock::reset();
+    let alloc = MockAlloc::new_with(AllocOptions::new());
+
+    unsafe {
+        // Small blocks share a slab page
+        let small = Layout::from_size_align(32, 8).unwrap();
+        let first = alloc.alloc(small);
+        let second = alloc.alloc(small);
+        assert!(!first.is_null() && !second.is_null());
+        assert_eq!(mock::locked_bytes(), 0x1000);
+        first.write_bytes(0xAA, 32);
+
+        // Growing past the size classes moves the block to pages of
+        // its own
+        let grown = alloc.realloc(first, small, 0x1800);
+        assert!(!grown.is_null());
+        assert_eq!(mock::locked_bytes(), 0x1000 + 0x2000);
+        assert!(slice::from_raw_parts(grown, 32).iter().all(|&x| x == 0xAA));
+
+        // Shrinking moves it back into a slab page
+        let large = Layout::from_size_align(0x1800, 8).unwrap();
+        let shrunk = alloc.realloc(grown, large, 16);
+        assert!(!shrunk.is_null());
+        assert!(slice::from_raw_parts(shrunk, 16).iter().all(|&x| x == 0xAA));
+        assert_eq!(mock::locked_bytes(), 0x2000);
+
+        alloc.dealloc(second, small);
+        alloc.dealloc(shrunk, Layout::from_size_align(16, 8).unwrap());
+    }
+    assert_eq!(mock::locked_bytes(), 0);
+    assert_eq!(mock::region_count(), 0);
+    assert_frees_wiped();
+}
+
+#[test]
+fn global_alloc_failure() {
+    mock::reset();
+    let alloc = MockAlloc::new_with(AllocOptions::new());
+
+    mock::fail_mmap_on(1);
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    assert!(unsafe { alloc.alloc(layout) }.is_null());
+
+    mock::set_memlock_limit(Some(0x1000));
+    let large = Layout::from_size_align(0x2000, 8).unwrap();
+    assert!(unsafe { alloc.alloc(large) }.is_null());
+    assert_eq!(mock::region_count(), 0);
+}
+
+#[cfg(feature = "allocator-api")]
+#[test]
+fn collections() {
+    mock::reset();
+    let alloc = MockAlloc::new_with(AllocOptions::new());
+
+    let mut keys = Vec::new_in(&alloc);
+    for i in 0..1000u32 {
+        keys.push([i; 8]);
+    }
+    let name = Box::new_in(*b"secret", &alloc);
+    assert!(keys
+        .iter()
+        .enumerate()
+        .all(|(i, key)| *key == [i as u32; 8]));
+    assert_eq!(&*name, b"secret");
+    assert!(mock::locked_bytes() >= 1000 * 32);
+
+    drop(keys);
+    drop(name);
+    assert_eq!(mock::region_count(), 0);
+    assert_frees_wiped();
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "mock")]
+
+use unswap::mock::{self, Call, MockImpl};
+use unswap::{AllocOptions, Error, UnswapArena};
+
+type MockArena = UnswapArena<MockImpl>;
+
+fn arena(capacity: usize) -> MockArena {
+    MockArena::try_with_capacity_with(AllocOptions::new(), capacity).unwrap()
+}
+
+#[test]
+fn full() {
+    mock::reset();
+    let arena = arena(0x1000);
+    assert_eq!(arena.capacity(), 0x1000);
+
+    arena.try_alloc_slice_zeroed::<u8>(0xFF0).unwrap();
+    assert!(matches!(
+        arena.try_alloc_slice_zeroed::<u8>(0x11),
+        Err(Error::ArenaFull)
+    ));
+    assert!(matches!(
+        arena.try_alloc_slice_zeroed::<u8>(usize::MAX),
+        Err(Error::LayoutError)
+    ));
+    // A failed allocation takes nothing, the rest still fits
+    assert_eq!(arena.used(), 0xFF0);
+    arena.try_alloc_slice_zeroed::<u8>(0x10).unwrap();
+    assert!(matches!(arena.try_alloc(0u8), Err(Error::ArenaFull)));
+
+    // Zero-sized values fit even in a full arena
+    arena.try_alloc(()).unwrap();
+    assert_eq!(arena.used(), 0x1000);
+}
+
+#[test]
+#[should_panic(expected = "Arena has no room left")]
+fn full_panics() {
+    mock::reset();
+    let arena = arena(16);
+    arena.alloc_str(&"x".repeat(0x1001));
+}
+
+#[test]
+fn alignment_padding() {
+    mock::reset();
+    let arena = arena(0x1000);
+
+    let byte = arena.alloc(1u8) as *mut u8 as usize;
+    let word = arena.alloc(2u64) as *mut u64 as usize;
+    assert_eq!(word % 8, 0);
+    assert_eq!(word - byte, 8);
+    assert_eq!(arena.used(), 16);
+
+    let short = arena.alloc(3u16) as *mut u16 as usize;
+    let block = arena.alloc_zeroed::<[u128; 2]>() as *mut [u128; 2] as usize;
+    assert_eq!(short - byte, 16);
+    assert_eq!(block % 16, 0);
+    assert_eq!(block - byte, 32);
+    assert_eq!(arena.used(), 64);
+}
+
+#[test]
+fn over_page_alignment() {
+    mock::reset();
+    let arena = arena(0x4000);
+
+    #[derive(Clone, Copy)]
+    #[repr(align(8192))]
+    struct Aligned([u8; 16]);
+
+    let base = arena.alloc(0u8) as *mut u8 as usize;
+    let value = arena.alloc(Aligned([7; 16]));
+    assert_eq!(value.0, [7; 16]);
+    let value = value as *mut Aligned as usize;
+    assert_eq!(value % 0x2000, 0);
+    assert!(value > base);
+    assert_eq!(arena.used(), value - base + 0x2000);
+
+    // The padding leaves no room for another one
+    let used = arena.used();
+    assert!(matches!(
+        arena.try_alloc(Aligned([0; 16])),
+        Err(Error::ArenaFull)
+    ));
+    assert_eq!(arena.used(), used);
+}
+
+#[test]
+fn reset_wipes() {
+    mock::reset();
+    let mut arena = arena(0x1000);
+
+    let at = arena.alloc_slice_copy(&[0xAAu8; 100]).as_ptr();
+    arena.alloc_str("hunter2");
+    arena.alloc_slice_from_fn(10, |i| i as u32);
+    assert!(mock::contents(at as *const _).unwrap()[..100]
+        .iter()
+        .all(|&x| x == 0xAA));
+
+    arena.reset();
+    assert_eq!(arena.used(), 0);
+    assert!(mock::contents(at as *const _)
+        .unwrap()
+        .iter()
+        .all(|&x| x == 0));
+
+    // The region is reused from the start
+    assert_eq!(arena.alloc(5u8) as *mut u8 as *const u8, at);
+    drop(arena);
+    assert!(matches!(mock::calls()[1], Call::Free { wiped: true, .. }));
+}
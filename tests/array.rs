@@ -0,0 +1,149 @@
+#![cfg(feature = "mock")]
+
+use std::cell::Cell;
+use std::mem::MaybeUninit;
+use std::panic::{self, AssertUnwindSafe};
+use unswap::mock::{self, Call, MockImpl};
+use unswap::{AllocOptions, UnswapArray};
+
+type MockArray<T> = UnswapArray<T, MockImpl>;
+
+/// Counts how many times it was dropped
+struct Counted<'a>(&'a Cell<usize>);
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+/// Iterator reporting a length of `reported`, while yielding
+/// `actual` elements
+struct Lying {
+    reported: usize,
+    actual: usize,
+    yielded: usize,
+}
+
+impl Iterator for Lying {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.yielded == self.actual {
+            return None;
+        }
+        self.yielded += 1;
+        Some(self.yielded)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.reported, Some(self.reported))
+    }
+}
+
+impl ExactSizeIterator for Lying {}
+
+#[test]
+fn destructors_run_on_drop() {
+    mock::reset();
+
+    let drops = Cell::new(0);
+    let array = MockArray::try_from_fn_with(AllocOptions::new(), 10, |_| Counted(&drops)).unwrap();
+    assert_eq!(drops.get(), 0);
+    drop(array);
+    assert_eq!(drops.get(), 10);
+    assert!(matches!(mock::calls()[1], Call::Free { wiped: true, .. }));
+}
+
+#[test]
+fn destructors_run_on_wipe() {
+    mock::reset();
+
+    let drops = Cell::new(0);
+    let mut array =
+        MockArray::try_from_fn_with(AllocOptions::new(), 10, |_| Counted(&drops)).unwrap();
+    array.wipe();
+    assert_eq!(drops.get(), 10);
+    assert_eq!(array.len(), 0);
+    let region = mock::contents(array.as_ptr() as *const _).unwrap();
+    assert!(region.iter().all(|&x| x == 0));
+
+    // The elements are not dropped again
+    drop(array);
+    assert_eq!(drops.get(), 10);
+}
+
+#[test]
+fn from_fn_panic_drops_written_elements() {
+    mock::reset();
+
+    let drops = Cell::new(0);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        MockArray::try_from_fn_with(AllocOptions::new(), 10, |i| {
+            assert!(i < 3, "out of values");
+            Counted(&drops)
+        })
+    }));
+    assert!(result.is_err());
+
+    // Only the three elements written before the panic are dropped,
+    // and the pages are still wiped and released
+    assert_eq!(drops.get(), 3);
+    assert_eq!(mock::region_count(), 0);
+    assert!(matches!(mock::calls()[1], Call::Free { wiped: true, .. }));
+}
+
+#[test]
+fn from_iter_reporting_more() {
+    mock::reset();
+
+    let iter = Lying {
+        reported: 5,
+        actual: 3,
+        yielded: 0,
+    };
+    let array = MockArray::try_from_iter_with(AllocOptions::new(), iter).unwrap();
+    assert_eq!(&array[..], &[1, 2, 3]);
+}
+
+#[test]
+fn from_iter_reporting_less() {
+    mock::reset();
+
+    let mut iter = Lying {
+        reported: 2,
+        actual: 10,
+        yielded: 0,
+    };
+    let array = MockArray::try_from_iter_with(AllocOptions::new(), &mut iter).unwrap();
+    assert_eq!(&array[..], &[1, 2]);
+    // Nothing past the reported length is taken
+    assert_eq!(iter.yielded, 2);
+}
+
+#[test]
+fn zeroed() {
+    mock::reset();
+
+    let array = MockArray::<[u64; 4]>::try_zeroed_with(AllocOptions::new(), 300).unwrap();
+    assert_eq!(array.len(), 300);
+    assert!(array.iter().all(|x| *x == [0; 4]));
+    assert_eq!(mock::locked_bytes(), 0x3000);
+
+    let empty = MockArray::<u64>::try_zeroed_with(AllocOptions::new(), 0).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(mock::region_count(), 1);
+}
+
+#[test]
+fn uninit() {
+    mock::reset();
+
+    let mut array = MockArray::<u32>::try_new_uninit_with(AllocOptions::new(), 100).unwrap();
+    assert_eq!(array.len(), 100);
+    for (i, slot) in array.iter_mut().enumerate() {
+        *slot = MaybeUninit::new(i as u32 * 3);
+    }
+    let array = unsafe { array.assume_init() };
+    assert!(array.iter().enumerate().all(|(i, &x)| x == i as u32 * 3));
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "mock")]
+
+use std::cell::Cell;
+use unswap::mock::{self, Call, MockImpl};
+use unswap::{AllocOptions, UnswapArray, UnswapBox};
+
+struct Counted<'a>(&'a Cell<usize>);
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn mutable_slice() {
+    mock::reset();
+
+    let mut values =
+        UnswapBox::<[u32], MockImpl>::try_from_slice_with(AllocOptions::new(), &[1, 2, 3]).unwrap();
+    values[1] = 5;
+    values.reverse();
+    assert_eq!(&values[..], &[3, 5, 1]);
+
+    drop(values);
+    assert!(matches!(mock::calls()[1], Call::Free { wiped: true, .. }));
+}
+
+#[test]
+fn mutable_str() {
+    mock::reset();
+
+    let mut text =
+        UnswapBox::<str, MockImpl>::try_from_str_with(AllocOptions::new(), "hunter2").unwrap();
+    text.make_ascii_uppercase();
+    assert_eq!(&*text, "HUNTER2");
+}
+
+#[test]
+fn slice_from_array() {
+    mock::reset();
+
+    let drops = Cell::new(0);
+    let array =
+        UnswapArray::<_, MockImpl>::try_from_fn_with(AllocOptions::new(), 4, |_| Counted(&drops))
+            .unwrap();
+    let at = array.as_ptr();
+    let mut values = UnswapBox::from(array);
+    assert_eq!(values.as_ptr(), at);
+    assert_eq!(mock::region_count(), 1);
+
+    values[0] = Counted(&drops);
+    assert_eq!(drops.get(), 1);
+    drop(values);
+    assert_eq!(drops.get(), 5);
+    assert_eq!(mock::region_count(), 0);
+}
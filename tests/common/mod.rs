@@ -0,0 +1,61 @@
+#![allow(dead_code)]
+
+use std::fs;
+
+/// Mapping listed in `/proc/self/smaps`
+pub struct Mapping {
+    pub start: usize,
+    pub end: usize,
+    /// Permissions, e.g. `rw-p`
+    pub perms: String,
+    pub flags: Vec<String>,
+}
+
+impl Mapping {
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+}
+
+/// Returns the mapping containing `addr`
+pub fn mapping(addr: usize) -> Mapping {
+    let smaps = fs::read_to_string("/proc/self/smaps").unwrap();
+    let mut found: Option<Mapping> = None;
+    for line in smaps.lines() {
+        if let Some(flags) = line.strip_prefix("VmFlags:") {
+            if let Some(mut mapping) = found {
+                mapping.flags = flags.split_whitespace().map(String::from).collect();
+                return mapping;
+            }
+        } else if let Some((range, rest)) = line.split_once(' ') {
+            if let Some((start, end)) = range.split_once('-') {
+                if let (Ok(start), Ok(end)) = (
+                    usize::from_str_radix(start, 16),
+                    usize::from_str_radix(end, 16),
+                ) {
+                    found = if (start..end).contains(&addr) {
+                        let perms = rest.split_whitespace().next().unwrap().to_owned();
+                        Some(Mapping {
+                            start,
+                            end,
+                            perms,
+                            flags: Vec::new(),
+                        })
+                    } else {
+                        None
+                    };
+                }
+            }
+        }
+    }
+    panic!("No mapping found for {:#x}", addr);
+}
+
+/// Returns `true` if all of the `size` bytes at `addr` are mapped
+pub fn is_mapped(addr: usize, size: usize) -> bool {
+    unsafe { libc::msync(addr as *mut libc::c_void, size, libc::MS_ASYNC) == 0 }
+}
+
+pub fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
@@ -0,0 +1,27 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use unswap::{AllocOptions, Backend, UnswapArray};
+
+#[test]
+fn excluded_from_core_dumps() {
+    let array = UnswapArray::new(0xAAu8, 8192);
+    let mapping = common::mapping(array.as_ptr() as usize);
+
+    assert!(mapping.has_flag("dd"));
+    assert!(mapping.has_flag("lo"));
+}
+
+#[test]
+fn dump_opt_out() {
+    let options = AllocOptions {
+        dont_dump: false,
+        backend: Backend::Mlock,
+        ..AllocOptions::new()
+    };
+    let array: UnswapArray<u8> = UnswapArray::try_new_with(options, 0xAA, 8192).unwrap();
+    let mapping = common::mapping(array.as_ptr() as usize);
+
+    assert!(!mapping.has_flag("dd"));
+}
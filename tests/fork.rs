@@ -0,0 +1,76 @@
+#![cfg(target_os = "linux")]
+
+use std::ptr;
+use unswap::{AllocOptions, Backend, ForkPolicy, UnswapArray};
+
+enum Outcome {
+    Exited(i32),
+    Signaled(i32),
+}
+
+/// Forks and reads the first element of `array` in the child,
+/// which exits with the value read as its status
+fn read_in_child(array: &UnswapArray<u8>) -> Outcome {
+    let at = array.as_ptr();
+    unsafe {
+        let pid = libc::fork();
+        assert!(pid >= 0);
+        if pid == 0 {
+            libc::_exit(ptr::read_volatile(at) as i32);
+        }
+
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+        if libc::WIFSIGNALED(status) {
+            Outcome::Signaled(libc::WTERMSIG(status))
+        } else {
+            Outcome::Exited(libc::WEXITSTATUS(status))
+        }
+    }
+}
+
+fn array_with(fork: ForkPolicy) -> UnswapArray<u8> {
+    let options = AllocOptions {
+        fork,
+        backend: Backend::Mlock,
+        ..AllocOptions::new()
+    };
+    UnswapArray::try_new_with(options, 0xAA, 4096).unwrap()
+}
+
+#[test]
+fn inherit() {
+    let array = array_with(ForkPolicy::Inherit);
+    assert!(matches!(read_in_child(&array), Outcome::Exited(0xAA)));
+}
+
+#[test]
+fn wipe_on_fork() {
+    let array = array_with(ForkPolicy::WipeOnFork);
+    assert!(matches!(read_in_child(&array), Outcome::Exited(0)));
+    assert_eq!(array[0], 0xAA);
+}
+
+#[test]
+fn dont_fork() {
+    let array = array_with(ForkPolicy::DontFork);
+    assert!(matches!(
+        read_in_child(&array),
+        Outcome::Signaled(libc::SIGSEGV)
+    ));
+    assert_eq!(array[0], 0xAA);
+}
+
+#[test]
+fn secret_dont_fork() {
+    let options = AllocOptions {
+        fork: ForkPolicy::DontFork,
+        backend: Backend::MemfdSecret,
+        ..AllocOptions::new()
+    };
+    let array = UnswapArray::try_new_with(options, 0xAA, 4096).unwrap();
+    assert!(matches!(
+        read_in_child(&array),
+        Outcome::Signaled(libc::SIGSEGV)
+    ));
+}
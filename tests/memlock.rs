@@ -0,0 +1,119 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::page_size;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::process::{self, Command};
+use unswap::{memlock, AllocOptions, Backend, UnswapArray};
+
+/// Set when running as the child of [limited_process]
+const CHILD: &str = "UNSWAP_MEMLOCK_CHILD";
+const LIMIT: usize = 0x100000;
+
+fn set_limit(soft: usize, hard: usize) {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &limit) }, 0);
+}
+
+const OPTIONS: AllocOptions = AllocOptions {
+    guard_pages: true,
+    backend: Backend::Mlock,
+    ..AllocOptions::new()
+};
+
+#[test]
+fn locked_bytes_grow() {
+    let before = memlock::locked_bytes().unwrap();
+    let small: UnswapArray<u8> = UnswapArray::try_new_with(OPTIONS, 0, 3).unwrap();
+    let large: UnswapArray<u8> = UnswapArray::try_new_with(OPTIONS, 0, page_size() + 1).unwrap();
+
+    // Whole pages are locked, but not the guard pages around them
+    assert_eq!(memlock::locked_bytes().unwrap(), before + 3 * page_size());
+    drop(small);
+    drop(large);
+    assert_eq!(memlock::locked_bytes().unwrap(), before);
+}
+
+#[test]
+fn raise_limit() {
+    let before = memlock::limit().unwrap();
+    let after = memlock::raise_limit().unwrap();
+    assert_eq!(after.soft, after.hard);
+    assert_eq!(after.hard, before.hard);
+    assert_eq!(memlock::limit().unwrap(), after);
+}
+
+/// Checks the limits of a process without `CAP_IPC_LOCK`, which
+/// nothing else in it has locked memory yet
+fn check_limited() {
+    set_limit(LIMIT / 2, LIMIT);
+    assert!(!memlock::is_unrestricted().unwrap());
+    assert_eq!(memlock::locked_bytes().unwrap(), 0);
+    assert_eq!(memlock::available().unwrap(), Some(LIMIT / 2));
+
+    let limit = memlock::raise_limit().unwrap();
+    assert_eq!(limit.soft, Some(LIMIT));
+    assert_eq!(limit.hard, Some(LIMIT));
+
+    // Every allocation takes whole pages
+    let pages = LIMIT / page_size();
+    assert_eq!(memlock::estimate_fit(0).unwrap(), Some(pages));
+    assert_eq!(memlock::estimate_fit(32).unwrap(), Some(pages));
+    assert_eq!(memlock::estimate_fit(page_size()).unwrap(), Some(pages));
+    assert_eq!(
+        memlock::estimate_fit(page_size() + 1).unwrap(),
+        Some(pages / 2)
+    );
+    assert!(memlock::estimate_fit(usize::MAX).is_err());
+
+    let array: UnswapArray<u8> = UnswapArray::try_new_with(OPTIONS, 0, 3 * page_size()).unwrap();
+    assert_eq!(memlock::locked_bytes().unwrap(), 3 * page_size());
+    assert_eq!(memlock::estimate_fit(32).unwrap(), Some(pages - 3));
+    drop(array);
+}
+
+#[test]
+fn limited_process() {
+    if env::var_os(CHILD).is_some() {
+        check_limited();
+        return;
+    }
+
+    // Run the check in a fresh process, so the limit of this one
+    // and the memory locked by other tests do not matter
+    let mut exe = env::current_exe().unwrap();
+    let root = unsafe { libc::geteuid() } == 0;
+    if root {
+        // The build directory may not be reachable by other users
+        let copy = env::temp_dir().join(format!("unswap-memlock-{}", process::id()));
+        fs::copy(&exe, &copy).unwrap();
+        fs::set_permissions(&copy, fs::Permissions::from_mode(0o755)).unwrap();
+        exe = copy;
+    }
+    let mut command = Command::new(&exe);
+    command
+        .args(["--exact", "limited_process", "--test-threads=1"])
+        .env(CHILD, "1");
+    if root {
+        // Root is not bound by the limit
+        command.uid(65534).gid(65534);
+    }
+    let output = command.output();
+    if root {
+        fs::remove_file(&exe).unwrap();
+    }
+
+    let output = output.unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
@@ -0,0 +1,176 @@
+#![cfg(feature = "mock")]
+
+use unswap::mock::{self, Call, MockImpl};
+use unswap::{AllocOptions, Protection, UnswapArray};
+
+type MockArray<T> = UnswapArray<T, MockImpl>;
+
+#[test]
+fn allocation_is_recorded() {
+    mock::reset();
+
+    let array = MockArray::try_new_with(AllocOptions::new(), 1u8, 100).unwrap();
+    let at = array.as_ptr() as usize;
+    assert_eq!(mock::locked_bytes(), 0x1000);
+    drop(array);
+
+    let calls = mock::calls();
+    assert_eq!(calls.len(), 2);
+    assert!(matches!(calls[0], Call::Alloc { at: Some(a), .. } if a == at));
+    assert!(matches!(calls[1], Call::Free { at: a, wiped: true, .. } if a == at));
+    assert_eq!(mock::locked_bytes(), 0);
+    assert_eq!(mock::region_count(), 0);
+}
+
+#[test]
+fn memlock_limit() {
+    mock::reset();
+    mock::set_page_size(0x4000);
+    mock::set_memlock_limit(Some(0x8000));
+
+    let first = MockArray::try_new_with(AllocOptions::new(), 0u8, 0x5000).unwrap();
+    let error = MockArray::try_new_with(AllocOptions::new(), 0u8, 1)
+        .err()
+        .unwrap();
+    assert!(error.is_lock_limit());
+
+    drop(first);
+    assert!(MockArray::try_new_with(AllocOptions::new(), 0u8, 0x8000).is_ok());
+}
+
+#[test]
+fn injected_failures() {
+    mock::reset();
+    mock::fail_mmap_on(2);
+    mock::fail_mlock_on(3);
+
+    let options = AllocOptions::new();
+    assert!(MockArray::try_new_with(options, 0u8, 1).is_ok());
+    let error = MockArray::try_new_with(options, 0u8, 1).err().unwrap();
+    assert_eq!(error.raw_os_error(), Some(libc::ENOMEM));
+    assert!(MockArray::try_new_with(options, 0u8, 1).is_ok());
+    let error = MockArray::try_new_with(options, 0u8, 1).err().unwrap();
+    assert_eq!(error.raw_os_error(), Some(libc::EAGAIN));
+    assert!(MockArray::try_new_with(options, 0u8, 1).is_ok());
+    assert_eq!(mock::region_count(), 0);
+}
+
+#[test]
+fn sealing() {
+    mock::reset();
+
+    let array = MockArray::try_new_with(AllocOptions::new(), 7u32, 16).unwrap();
+    let at = array.as_ptr() as *const _;
+    let sealed = array.seal(Protection::NoAccess).unwrap();
+    assert_eq!(mock::protection(at), Some(Protection::NoAccess));
+    {
+        let outer = sealed.read().unwrap();
+        let inner = sealed.read().unwrap();
+        assert_eq!(mock::protection(at), Some(Protection::ReadOnly));
+        assert_eq!(outer[0] + inner[15], 14);
+    }
+    assert_eq!(mock::protection(at), Some(Protection::NoAccess));
+}
+
+#[test]
+fn failed_reseal() {
+    mock::reset();
+
+    let array = MockArray::try_new_with(AllocOptions::new(), 7u32, 16).unwrap();
+    let at = array.as_ptr() as *const _;
+    let mut sealed = array.seal(Protection::NoAccess).unwrap();
+    {
+        let mut guard = sealed.write().unwrap();
+        guard[0] = 8;
+        mock::fail_mprotect_on(1);
+    }
+    // The guard could not seal the pages again, but did not panic
+    assert!(!sealed.is_sealed());
+    assert_eq!(mock::protection(at), Some(Protection::ReadWrite));
+
+    drop(sealed.read().unwrap());
+    assert!(sealed.is_sealed());
+    assert_eq!(mock::protection(at), Some(Protection::NoAccess));
+}
+
+#[test]
+fn failed_unseal_on_drop() {
+    mock::reset();
+
+    let sealed = MockArray::try_new_with(AllocOptions::new(), 7u32, 16)
+        .unwrap()
+        .seal(Protection::NoAccess)
+        .unwrap();
+    mock::fail_mprotect_on(1);
+    drop(sealed);
+
+    // The pages are released without being touched
+    assert_eq!(mock::region_count(), 0);
+    assert!(matches!(
+        mock::calls().last(),
+        Some(Call::Free { wiped: false, .. })
+    ));
+}
+
+#[test]
+fn resize_copies_before_freeing() {
+    mock::reset();
+
+    let mut array = MockArray::try_new_with(AllocOptions::new(), 7u64, 100).unwrap();
+    let old = array.as_ptr() as usize;
+    array.try_resize(1000, 8).unwrap();
+    let new = array.as_ptr() as usize;
+    assert!(array[..100].iter().all(|&x| x == 7));
+    assert!(array[100..].iter().all(|&x| x == 8));
+
+    // The old region is wiped and released only once the new one
+    // holds the elements
+    let calls = mock::calls();
+    assert_eq!(calls.len(), 3);
+    assert!(matches!(calls[1], Call::Alloc { at: Some(at), .. } if at == new));
+    assert!(matches!(calls[2], Call::Free { at, wiped: true, .. } if at == old));
+
+    array.truncate(10);
+    array.shrink_to_fit();
+    assert_ne!(array.as_ptr() as usize, new);
+    assert!(array.iter().all(|&x| x == 7));
+    let calls = mock::calls();
+    assert!(matches!(calls[3], Call::Alloc { layout, .. } if layout.size() == 0x1000));
+    assert!(matches!(calls[4], Call::Free { at, wiped: true, .. } if at == new));
+    assert_eq!(mock::region_count(), 1);
+}
+
+#[test]
+fn failed_resize_keeps_array() {
+    mock::reset();
+
+    let mut array = MockArray::try_new_with(AllocOptions::new(), 7u8, 0x1000).unwrap();
+    let at = array.as_ptr();
+    mock::set_memlock_limit(Some(0x1000));
+    assert!(array.try_resize(0x1001, 8).err().unwrap().is_lock_limit());
+    assert_eq!(array.as_ptr(), at);
+    assert_eq!(array.len(), 0x1000);
+    assert!(array.iter().all(|&x| x == 7));
+}
+
+#[test]
+fn shrink_to_zero() {
+    mock::reset();
+
+    let mut array = MockArray::try_new_with(AllocOptions::new(), 7u32, 0x1000).unwrap();
+    let at = array.as_ptr() as usize;
+    array.truncate(0);
+    array.shrink_to_fit();
+
+    // Nothing is left allocated
+    assert_eq!(array.as_ptr() as usize, std::mem::align_of::<u32>());
+    assert_eq!(mock::region_count(), 0);
+    assert!(matches!(
+        mock::calls().last(),
+        Some(Call::Free { at: a, wiped: true, .. }) if *a == at
+    ));
+
+    array.resize(2, 9);
+    assert_eq!(&array[..], &[9, 9]);
+    assert_eq!(mock::region_count(), 1);
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "mock")]
+
+use std::alloc::Layout;
+use unswap::mock::{self, MockImpl};
+use unswap::{AllocOptions, Error, OsImpl, UnswapArray};
+
+#[test]
+fn page_align_16k() {
+    mock::reset();
+    mock::set_page_size(0x4000);
+
+    assert_eq!(MockImpl::page_align(0), Some(0));
+    assert_eq!(MockImpl::page_align(1), Some(0x4000));
+    assert_eq!(MockImpl::page_align(0x1000), Some(0x4000));
+    assert_eq!(MockImpl::page_align(0x4000), Some(0x4000));
+    assert_eq!(MockImpl::page_align(0x4001), Some(0x8000));
+    assert_eq!(MockImpl::page_align(usize::MAX - 0x1000), None);
+
+    assert!(MockImpl::is_page_aligned(0x8000));
+    assert!(!MockImpl::is_page_aligned(0x1000));
+    assert!(!MockImpl::is_page_aligned(0x6000));
+}
+
+#[test]
+fn page_align_64k() {
+    mock::reset();
+    mock::set_page_size(0x10000);
+
+    assert_eq!(MockImpl::page_align(1), Some(0x10000));
+    assert_eq!(MockImpl::page_align(0x4000), Some(0x10000));
+    assert_eq!(MockImpl::page_align(0x10001), Some(0x20000));
+
+    assert!(MockImpl::is_page_aligned(0x20000));
+    assert!(!MockImpl::is_page_aligned(0x4000));
+}
+
+#[test]
+fn alloc_rejects_unaligned_size() {
+    mock::reset();
+    mock::set_page_size(0x4000);
+
+    let options = AllocOptions::new();
+
+    let layout = Layout::from_size_align(0x1000, 1).unwrap();
+    assert!(matches!(
+        MockImpl::alloc_pages(layout, &options),
+        Err(Error::AlignError)
+    ));
+
+    let layout = Layout::from_size_align(0x8000, 1).unwrap();
+    let pages = MockImpl::alloc_pages(layout, &options).unwrap();
+    assert_eq!(pages as usize % 0x4000, 0);
+    unsafe {
+        MockImpl::free_pages(pages, layout, &options);
+    }
+}
+
+#[test]
+fn array_in_64k_pages() {
+    mock::reset();
+    mock::set_page_size(0x10000);
+
+    let array =
+        UnswapArray::<u32, MockImpl>::try_new_with(AllocOptions::new(), 0xAA55, 0x5000).unwrap();
+    assert_eq!(array.as_ptr() as usize % 0x10000, 0);
+    assert!(array.iter().all(|&x| x == 0xAA55));
+    assert_eq!(mock::locked_bytes(), 0x20000);
+}
@@ -0,0 +1,133 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::{is_mapped, page_size};
+use std::alloc::Layout;
+use std::sync::mpsc;
+use std::sync::{Mutex, MutexGuard, Once};
+use std::thread;
+use std::time::Duration;
+use unswap::pool::{self, Policy, PoolImpl};
+use unswap::{AllocOptions, Error, OsImpl, UnswapArray};
+
+type PoolArray = UnswapArray<u8, PoolImpl>;
+
+const POOL_PAGES: usize = 16;
+
+/// The pool is global, so the tests take turns using it
+static SERIAL: Mutex<()> = Mutex::new(());
+static INIT: Once = Once::new();
+
+fn setup(policy: Policy) -> MutexGuard<'static, ()> {
+    let guard = SERIAL
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    INIT.call_once(|| {
+        pool::init(AllocOptions::new(), POOL_PAGES * page_size(), Policy::Fail).unwrap();
+    });
+    assert_eq!(pool::remaining(), pool::capacity());
+    pool::set_policy(policy);
+    guard
+}
+
+fn pages(count: usize) -> Result<PoolArray, Error> {
+    PoolArray::try_new_with(AllocOptions::new(), 0xAA, count * page_size())
+}
+
+#[test]
+fn fragmentation() {
+    let _guard = setup(Policy::Fail);
+    assert_eq!(pool::capacity(), POOL_PAGES * page_size());
+
+    let mut arrays: Vec<_> = (0..POOL_PAGES).map(|_| Some(pages(1).unwrap())).collect();
+    assert_eq!(pool::remaining(), 0);
+    assert!(matches!(pages(1), Err(Error::PoolExhausted)));
+
+    for array in arrays.iter_mut().step_by(2) {
+        *array = None;
+    }
+    assert_eq!(pool::remaining(), POOL_PAGES / 2 * page_size());
+
+    // There is room for two pages, but not in a row
+    assert!(matches!(pages(2), Err(Error::PoolExhausted)));
+    assert_eq!(pool::remaining(), POOL_PAGES / 2 * page_size());
+
+    let single = pages(1).unwrap();
+    assert_eq!(pool::remaining(), (POOL_PAGES / 2 - 1) * page_size());
+
+    drop(arrays);
+    drop(single);
+    assert_eq!(pool::remaining(), pool::capacity());
+}
+
+#[test]
+fn fallback() {
+    let _guard = setup(Policy::Fallback);
+
+    let all = pages(POOL_PAGES).unwrap();
+    let base = all.as_ptr() as usize;
+    let outside = pages(2).unwrap();
+    let at = outside.as_ptr() as usize;
+    assert_eq!(pool::remaining(), 0);
+
+    // The pages come from DefaultImpl, and are locked just the same
+    assert!(at + 2 * page_size() <= base || at >= base + pool::capacity());
+    assert!(common::mapping(at).has_flag("lo"));
+    assert!(outside.iter().all(|&x| x == 0xAA));
+
+    // Releasing them unmaps them instead of growing the pool
+    drop(outside);
+    assert!(!is_mapped(at, 2 * page_size()));
+    assert_eq!(pool::remaining(), 0);
+
+    drop(all);
+    assert_eq!(pool::remaining(), pool::capacity());
+}
+
+#[test]
+fn block_until_returned() {
+    let _guard = setup(Policy::Block);
+
+    let all = pages(POOL_PAGES).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let waiter = thread::spawn(move || {
+        let array = pages(4).unwrap();
+        sender.send(()).unwrap();
+        array
+    });
+
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+    drop(all);
+    receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    let array = waiter.join().unwrap();
+    assert_eq!(pool::remaining(), (POOL_PAGES - 4) * page_size());
+
+    drop(array);
+    assert_eq!(pool::remaining(), pool::capacity());
+}
+
+#[test]
+fn block_never_fits() {
+    let _guard = setup(Policy::Block);
+
+    // Find an alignment the pool base does not have, no request
+    // spanning the whole pool can then ever be satisfied
+    let base = pages(POOL_PAGES).unwrap().as_ptr() as usize;
+    let align = (base & base.wrapping_neg()) * 2;
+    let layout = Layout::from_size_align(pool::capacity(), align).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let result = PoolImpl::alloc_pages(layout, &AllocOptions::new());
+        sender
+            .send(matches!(result, Err(Error::PoolExhausted)))
+            .unwrap();
+    });
+    assert!(receiver.recv_timeout(Duration::from_secs(5)).unwrap());
+
+    // Requests larger than the pool are refused as well
+    let result = pages(POOL_PAGES + 1);
+    assert!(matches!(result, Err(Error::PoolExhausted)));
+    assert_eq!(pool::remaining(), pool::capacity());
+}
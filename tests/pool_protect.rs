@@ -0,0 +1,54 @@
+//! Kept apart from the other pool tests, as pages which cannot be
+//! opened up again are lost to the pool for good.
+#![cfg(all(target_os = "linux", feature = "mock"))]
+
+mod common;
+
+use common::page_size;
+use std::alloc::Layout;
+use std::slice;
+use unswap::pool::{self, Policy, PoolImpl};
+use unswap::{AllocOptions, OsImpl, Protection, UnswapArray};
+
+type PoolArray = UnswapArray<u8, PoolImpl>;
+
+fn sealed_secret(pages: usize) -> (usize, unswap::SealedArray<u8, PoolImpl>) {
+    let array = PoolArray::try_new_with(AllocOptions::new(), 0xAA, pages * page_size()).unwrap();
+    let at = array.as_ptr() as usize;
+    (at, array.seal(Protection::NoAccess).unwrap())
+}
+
+#[test]
+fn sealed_pages_reused() {
+    pool::init(AllocOptions::new(), 4 * page_size(), Policy::Fail).unwrap();
+
+    // Unsealing fails when the array is dropped, but the pool opens
+    // up and wipes the pages on its own before reusing them
+    let (at, sealed) = sealed_secret(4);
+    pool::fail_mprotect(1);
+    drop(sealed);
+    assert_eq!(pool::remaining(), pool::capacity());
+
+    let layout = Layout::from_size_align(4 * page_size(), 1).unwrap();
+    let reused = PoolImpl::alloc_pages(layout, &AllocOptions::new()).unwrap() as *mut u8;
+    assert_eq!(reused as usize, at);
+    unsafe {
+        assert!(slice::from_raw_parts(reused, layout.size())
+            .iter()
+            .all(|&x| x == 0));
+        reused.write_bytes(0x55, layout.size());
+        PoolImpl::free_pages(reused as *mut _, layout, &AllocOptions::new());
+    }
+    assert_eq!(pool::remaining(), pool::capacity());
+
+    // If the pool cannot open them up either, they are never
+    // handed out again
+    let (_, sealed) = sealed_secret(1);
+    pool::fail_mprotect(2);
+    drop(sealed);
+    assert_eq!(pool::remaining(), pool::capacity() - page_size());
+
+    let rest = PoolArray::try_new_with(AllocOptions::new(), 0, 3 * page_size()).unwrap();
+    assert_ne!(rest.as_ptr() as usize, at);
+    assert_eq!(pool::remaining(), 0);
+}
@@ -0,0 +1,132 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::{is_mapped, page_size};
+use unswap::{AllocOptions, Backend, UnswapArray};
+
+fn options(guard_pages: bool, backend: Backend) -> AllocOptions {
+    AllocOptions {
+        guard_pages,
+        backend,
+        ..AllocOptions::new()
+    }
+}
+
+/// Checks that `array` holds `len` elements equal to their index
+fn assert_contents(array: &UnswapArray<usize>, len: usize) {
+    assert_eq!(array.len(), len);
+    assert!(array.iter().enumerate().all(|(i, &x)| x == i));
+}
+
+fn fill(array: &mut UnswapArray<usize>) {
+    for (i, x) in array.iter_mut().enumerate() {
+        *x = i;
+    }
+}
+
+/// Checks that the region holding `array` is locked, excluded from
+/// core dumps and surrounded by guard pages if `guard_pages` is set
+fn assert_attributes(array: &UnswapArray<usize>, guard_pages: bool) {
+    let at = array.as_ptr() as usize;
+    let size = array.len() * std::mem::size_of::<usize>();
+    for addr in (at..at + size).step_by(page_size()) {
+        let mapping = common::mapping(addr);
+        assert!(mapping.has_flag("lo"));
+        assert!(mapping.has_flag("dd"));
+    }
+    if guard_pages {
+        let end = at + size.div_ceil(page_size()) * page_size();
+        assert_eq!(common::mapping(at - page_size()).perms, "---p");
+        assert_eq!(common::mapping(end).perms, "---p");
+    }
+}
+
+fn round_trip(options: AllocOptions) {
+    let per_page = page_size() / std::mem::size_of::<usize>();
+    let mut array = UnswapArray::try_new_with(options, 0usize, per_page).unwrap();
+    fill(&mut array);
+
+    array.resize(per_page * 5 + 3, 0);
+    fill(&mut array);
+    assert_contents(&array, per_page * 5 + 3);
+    assert_attributes(&array, options.guard_pages);
+
+    array.resize(per_page + 1, 0);
+    assert_contents(&array, per_page + 1);
+    array.shrink_to_fit();
+    assert_contents(&array, per_page + 1);
+    assert_attributes(&array, options.guard_pages);
+
+    // Nothing is left mapped for an empty array
+    array.resize(0, 0);
+    array.shrink_to_fit();
+    assert_eq!(array.len(), 0);
+    assert_eq!(array.as_ptr() as usize, std::mem::align_of::<usize>());
+
+    array.resize(3, 7);
+    assert_eq!(&array[..], &[7, 7, 7]);
+    assert_attributes(&array, options.guard_pages);
+}
+
+#[test]
+fn round_trip_without_guard_pages() {
+    round_trip(options(false, Backend::Mlock));
+}
+
+#[test]
+fn round_trip_with_guard_pages() {
+    round_trip(options(true, Backend::Mlock));
+}
+
+#[test]
+fn round_trip_memfd_secret() {
+    round_trip(options(false, Backend::MemfdSecret));
+    round_trip(options(true, Backend::MemfdSecret));
+}
+
+#[test]
+fn growth_in_place() {
+    let per_page = page_size() / std::mem::size_of::<usize>();
+    let mut array =
+        UnswapArray::try_new_with(options(false, Backend::Mlock), 0, per_page * 4).unwrap();
+    fill(&mut array);
+
+    // Cutting off the tail always happens in place, and leaves room
+    // to grow back into
+    array.truncate(per_page);
+    array.shrink_to_fit();
+    let at = array.as_ptr() as usize;
+    assert_contents(&array, per_page);
+
+    let tail_free = !is_mapped(at + page_size(), 3 * page_size());
+    array.resize(per_page * 4, 0);
+    if tail_free {
+        assert_eq!(array.as_ptr() as usize, at);
+    }
+    fill(&mut array);
+    assert_contents(&array, per_page * 4);
+    assert_attributes(&array, false);
+}
+
+#[test]
+fn guard_pages_force_copy() {
+    let mut array = UnswapArray::try_new_with(options(true, Backend::Mlock), 0, 16).unwrap();
+    fill(&mut array);
+    let at = array.as_ptr() as usize;
+
+    array.resize(page_size(), 0);
+    assert_ne!(array.as_ptr() as usize, at);
+    assert!(array[..16].iter().enumerate().all(|(i, &x)| x == i));
+    assert!(array[16..].iter().all(|&x| x == 0));
+    assert_attributes(&array, true);
+
+    // Shrinking moves the elements too, the guard page after the
+    // region cannot move along with its end
+    let at = array.as_ptr() as usize;
+    array.truncate(16);
+    array.shrink_to_fit();
+    assert_ne!(array.as_ptr() as usize, at);
+    assert_contents(&array, 16);
+    assert_attributes(&array, true);
+}
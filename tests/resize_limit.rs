@@ -0,0 +1,87 @@
+//! Kept apart from the other resizing tests, as it forks and the
+//! child must not find the allocator locked by another thread.
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::{is_mapped, page_size};
+use unswap::{AllocOptions, Backend, UnswapArray};
+
+const SKIPPED: i32 = 77;
+
+fn set_limit(pages: usize) -> bool {
+    let limit = libc::rlimit {
+        rlim_cur: (pages * page_size()) as libc::rlim_t,
+        rlim_max: (pages * page_size()) as libc::rlim_t,
+    };
+    unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &limit) == 0 }
+}
+
+/// Fills two pages up to a limit of two locked pages, then tries
+/// to grow by one. Returns the exit status for the child.
+fn grow_past_limit() -> i32 {
+    // Root is not bound by the limit, so drop the privileges
+    unsafe {
+        if libc::geteuid() == 0 && (libc::setgid(65534) != 0 || libc::setuid(65534) != 0) {
+            return SKIPPED;
+        }
+    }
+    if !set_limit(3) {
+        return SKIPPED;
+    }
+
+    let options = AllocOptions {
+        guard_pages: false,
+        backend: Backend::Mlock,
+        ..AllocOptions::new()
+    };
+    let mut array: UnswapArray<u8> = match UnswapArray::try_new_with(options, 0xAA, 3 * page_size())
+    {
+        Ok(array) => array,
+        Err(_) => return SKIPPED,
+    };
+    // Cutting off the last page leaves room to grow back into
+    array.truncate(2 * page_size());
+    array.shrink_to_fit();
+    if !set_limit(2) {
+        return SKIPPED;
+    }
+    let at = array.as_ptr() as usize;
+    let end = at + 2 * page_size();
+    let tail_free = !is_mapped(end, page_size());
+
+    match array.try_resize(3 * page_size(), 0x55) {
+        Err(error) if error.is_lock_limit() => {}
+        _ => return 1,
+    }
+    if array.as_ptr() as usize != at || array.len() != 2 * page_size() {
+        return 2;
+    }
+    if !array.iter().all(|&x| x == 0xAA) {
+        return 3;
+    }
+    // Growing in place must not leave unlocked pages behind
+    if tail_free && is_mapped(end, page_size()) {
+        return 4;
+    }
+    0
+}
+
+#[test]
+fn failed_growth_is_rolled_back() {
+    let status = unsafe {
+        let pid = libc::fork();
+        assert!(pid >= 0);
+        if pid == 0 {
+            libc::_exit(grow_past_limit());
+        }
+
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+        assert!(libc::WIFEXITED(status));
+        libc::WEXITSTATUS(status)
+    };
+    if status != SKIPPED {
+        assert_eq!(status, 0);
+    }
+}
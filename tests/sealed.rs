@@ -0,0 +1,56 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use unswap::{Protection, UnswapArray};
+
+/// Returns the access permissions of the mapping containing `at`,
+/// leaving out whether it is private or shared
+fn perms(at: usize) -> String {
+    common::mapping(at).perms[..3].to_owned()
+}
+
+#[test]
+fn no_access_at_rest() {
+    let array = UnswapArray::new(5u64, 1024);
+    let at = array.as_ptr() as usize;
+    let mut sealed = array.seal(Protection::NoAccess).unwrap();
+    assert_eq!(perms(at), "---");
+    assert!(sealed.is_sealed());
+
+    {
+        let outer = sealed.read().unwrap();
+        let inner = sealed.read().unwrap();
+        assert_eq!(perms(at), "r--");
+        assert_eq!(outer[0] + inner[1023], 10);
+        drop(outer);
+        assert_eq!(perms(at), "r--");
+    }
+    assert_eq!(perms(at), "---");
+
+    {
+        let mut guard = sealed.write().unwrap();
+        guard[0] = 6;
+        assert_eq!(perms(at), "rw-");
+    }
+    assert_eq!(perms(at), "---");
+
+    let array = sealed.unseal().unwrap();
+    assert_eq!(perms(at), "rw-");
+    assert_eq!(array[0], 6);
+}
+
+#[test]
+fn read_only_at_rest() {
+    let array = UnswapArray::new(5u64, 1024);
+    let at = array.as_ptr() as usize;
+    let mut sealed = array.seal(Protection::ReadOnly).unwrap();
+    assert_eq!(perms(at), "r--");
+
+    drop(sealed.read().unwrap());
+    assert_eq!(perms(at), "r--");
+
+    sealed.write().unwrap()[0] = 6;
+    assert_eq!(perms(at), "r--");
+    assert_eq!(sealed.read().unwrap()[0], 6);
+}
@@ -0,0 +1,171 @@
+#![cfg(feature = "mock")]
+
+use std::alloc::Layout;
+use std::collections::HashSet;
+use std::mem;
+use std::ptr::NonNull;
+use unswap::mock::{self, Call, MockImpl};
+use unswap::{AllocOptions, Slab, SlabBox};
+
+type MockSlab = Slab<MockImpl>;
+
+fn page_of(at: NonNull<u8>) -> usize {
+    at.as_ptr() as usize & !0xFFF
+}
+
+/// Checks that every page released so far was wiped first
+fn assert_frees_wiped() {
+    for call in mock::calls() {
+        if let Call::Free { wiped, .. } = call {
+            assert!(wiped);
+        }
+    }
+}
+
+#[test]
+fn size_classes() {
+    mock::reset();
+    let slab = MockSlab::new(AllocOptions::new());
+
+    // (size, align, slot size)
+    let requests = [
+        (1, 1, 16),
+        (16, 8, 16),
+        (17, 1, 32),
+        (8, 32, 32),
+        (100, 4, 128),
+        (1024, 8, 1024),
+    ];
+    let blocks: Vec<_> = requests
+        .iter()
+        .map(|&(size, align, slot)| {
+            let layout = Layout::from_size_align(size, align).unwrap();
+            let at = slab.alloc(layout).unwrap();
+            assert_eq!(at.as_ptr() as usize % slot, 0);
+            (at, layout)
+        })
+        .collect();
+
+    // Requests of the same class share a page
+    assert_eq!(page_of(blocks[0].0), page_of(blocks[1].0));
+    assert_eq!(page_of(blocks[2].0), page_of(blocks[3].0));
+    let pages: HashSet<_> = blocks.iter().map(|&(at, _)| page_of(at)).collect();
+    assert_eq!(pages.len(), 4);
+    assert_eq!(slab.page_count(), 4);
+    assert_eq!(mock::region_count(), 4);
+
+    for (at, layout) in blocks {
+        unsafe { slab.free(at, layout) };
+    }
+    assert_eq!(slab.page_count(), 0);
+    assert_eq!(mock::region_count(), 0);
+    assert_frees_wiped();
+}
+
+#[test]
+fn mixed_order_frees() {
+    mock::reset();
+    let slab = MockSlab::new(AllocOptions::new());
+
+    let mut boxes: Vec<_> = (0..300u32)
+        .map(|i| Some(SlabBox::new_in(&slab, [i; 8])))
+        .collect();
+    assert_eq!(slab.page_count(), 3);
+
+    // Free every other value, then the rest backwards, leaving
+    // each page partially used in between
+    for i in (0..300).step_by(2) {
+        boxes[i] = None;
+    }
+    assert_eq!(slab.page_count(), 3);
+    for (i, value) in boxes.iter().enumerate() {
+        if let Some(value) = value {
+            assert_eq!(**value, [i as u32; 8]);
+        }
+    }
+
+    // Freed slots are reused before new pages are allocated
+    let refill: Vec<_> = (0..150u32)
+        .map(|i| SlabBox::new_in(&slab, [i; 8]))
+        .collect();
+    assert_eq!(slab.page_count(), 3);
+    drop(refill);
+
+    for i in (1..300).step_by(2).rev() {
+        boxes[i] = None;
+    }
+    assert_eq!(slab.page_count(), 0);
+    assert_eq!(mock::region_count(), 0);
+    assert_frees_wiped();
+}
+
+#[test]
+fn freed_slots_are_wiped() {
+    mock::reset();
+    let slab = MockSlab::new(AllocOptions::new());
+
+    let keep = SlabBox::new_in(&slab, [0x55u8; 64]);
+    let key = SlabBox::new_in(&slab, [0xAAu8; 64]);
+    let at = key.as_ptr() as usize;
+    let page = at & !0xFFF;
+    assert_eq!(page, keep.as_ptr() as usize & !0xFFF);
+    drop(key);
+
+    // The page is still allocated, so the slot can be inspected.
+    // Its first bytes hold the free list link now.
+    let region = mock::contents(page as *const _).unwrap();
+    let slot = &region[at - page..][..64];
+    assert!(slot[mem::size_of::<usize>()..].iter().all(|&x| x == 0));
+    assert!(keep.iter().all(|&x| x == 0x55));
+}
+
+#[test]
+fn drop_releases_leaked_pages() {
+    mock::reset();
+    let slab = MockSlab::new(AllocOptions::new());
+
+    for i in 0..200u64 {
+        mem::forget(SlabBox::new_in(&slab, i));
+        mem::forget(SlabBox::new_in(&slab, [i; 16]));
+    }
+    // 254 slots of 16 bytes and 31 slots of 128 bytes fit in a
+    // page next to the header
+    assert_eq!(slab.page_count(), 1 + 7);
+    drop(slab);
+
+    assert_eq!(mock::region_count(), 0);
+    assert_eq!(mock::locked_bytes(), 0);
+    assert_frees_wiped();
+}
+
+#[test]
+fn large_requests() {
+    mock::reset();
+    let slab = MockSlab::new(AllocOptions::new());
+
+    let layout = Layout::from_size_align(0x1800, 16).unwrap();
+    let at = slab.alloc(layout).unwrap();
+    assert_eq!(at.as_ptr() as usize % 0x1000, 0);
+    assert_eq!(slab.page_count(), 0);
+    assert!(matches!(
+        mock::calls().last(),
+        Some(Call::Alloc { layout, .. }) if layout.size() == 0x2000
+    ));
+
+    unsafe {
+        at.as_ptr().write_bytes(0xAA, layout.size());
+        slab.free(at, layout);
+    }
+    assert!(matches!(
+        mock::calls().last(),
+        Some(Call::Free { layout, wiped: true, .. }) if layout.size() == 0x2000
+    ));
+
+    // Anything above a quarter of a page gets pages of its own
+    let layout = Layout::from_size_align(0x401, 1).unwrap();
+    let at = slab.alloc(layout).unwrap();
+    assert_eq!(slab.page_count(), 0);
+    assert_eq!(mock::locked_bytes(), 0x1000);
+    unsafe { slab.free(at, layout) };
+    assert_eq!(mock::region_count(), 0);
+}
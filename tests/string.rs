@@ -0,0 +1,96 @@
+#![cfg(feature = "mock")]
+
+use std::fmt::Write;
+use unswap::mock::{self, MockImpl};
+use unswap::{AllocOptions, Error, UnswapString, UnswapVec};
+
+type MockString = UnswapString<MockImpl>;
+
+/// Returns the whole region holding the bytes of `text`, including
+/// the part past its length
+fn region(text: &MockString) -> Vec<u8> {
+    mock::contents(text.as_ptr() as *const _).unwrap()
+}
+
+#[test]
+fn push_and_format() {
+    mock::reset();
+
+    let mut text = MockString::new_with(AllocOptions::new());
+    text.push_str("key");
+    text.push('=');
+    write!(text, "{:04x}", 0xbeefu32).unwrap();
+    assert_eq!(text.as_str(), "key=beef");
+    assert_eq!(mock::region_count(), 1);
+}
+
+#[test]
+fn from_utf8() {
+    mock::reset();
+
+    let mut bytes = UnswapVec::<u8, MockImpl>::new_with(AllocOptions::new());
+    bytes.extend_from_slice("ünswap".as_bytes());
+    let at = bytes.as_ptr();
+    let text = MockString::from_utf8(bytes).unwrap();
+    assert_eq!(text.as_str(), "ünswap");
+    // The bytes are reused, not copied
+    assert_eq!(text.as_ptr(), at);
+    assert_eq!(mock::region_count(), 1);
+}
+
+#[test]
+fn from_utf8_rejects_invalid_bytes() {
+    mock::reset();
+
+    let mut bytes = UnswapVec::<u8, MockImpl>::new_with(AllocOptions::new());
+    bytes.extend_from_slice(b"ok\xFF\xFEok");
+    let at = bytes.as_ptr();
+
+    let error = MockString::from_utf8(bytes).err().unwrap();
+    assert_eq!(error.utf8_error().valid_up_to(), 2);
+    let bytes = error.into_bytes();
+    assert_eq!(&bytes[..], b"ok\xFF\xFEok");
+    assert_eq!(bytes.as_ptr(), at);
+}
+
+#[test]
+fn from_utf8_slice() {
+    mock::reset();
+
+    let text = MockString::try_from_utf8_slice_with(AllocOptions::new(), "ünswap".as_bytes());
+    assert_eq!(text.unwrap().as_str(), "ünswap");
+
+    let error = MockString::try_from_utf8_slice_with(AllocOptions::new(), b"\xC3")
+        .err()
+        .unwrap();
+    assert!(matches!(error, Error::Utf8Error(_)));
+    // Nothing is allocated for invalid bytes
+    assert_eq!(mock::calls().len(), 2);
+}
+
+#[test]
+fn removed_chars_are_wiped() {
+    mock::reset();
+
+    let mut text = MockString::new_with(AllocOptions::new());
+    text.push_str("hunter2 ünswap");
+    let capacity = text.capacity();
+
+    assert_eq!(text.pop(), Some('p'));
+    text.truncate(8);
+    assert_eq!(text.as_str(), "hunter2 ");
+    assert!(region(&text)[8..].iter().all(|&x| x == 0));
+
+    text.clear();
+    assert!(region(&text).iter().all(|&x| x == 0));
+    assert_eq!(text.capacity(), capacity);
+}
+
+#[test]
+#[should_panic(expected = "Truncating not on a char boundary")]
+fn truncate_inside_char() {
+    mock::reset();
+
+    let mut text = MockString::try_from_str_with(AllocOptions::new(), "ünswap").unwrap();
+    text.truncate(1);
+}
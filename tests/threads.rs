@@ -0,0 +1,39 @@
+#![cfg(target_os = "linux")]
+
+use std::sync::Arc;
+use std::thread;
+use unswap::{UnswapArray, UnswapBox, UnswapString, UnswapVec};
+
+#[test]
+fn containers_move_between_threads() {
+    let key = UnswapBox::new([7u8; 32]);
+    let text = UnswapBox::<str>::try_from_str("hunter2").unwrap();
+    let mut vec = UnswapVec::new();
+    vec.extend_from_slice(&[1u32, 2, 3]);
+    let string = UnswapString::from("secret");
+
+    let sum = thread::spawn(move || {
+        key.iter().map(|&x| x as usize).sum::<usize>()
+            + text.len()
+            + vec.iter().sum::<u32>() as usize
+            + string.len()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(sum, 224 + 7 + 6 + 6);
+}
+
+#[test]
+fn containers_shared_between_threads() {
+    let array = Arc::new(UnswapArray::new(3u64, 512));
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let array = Arc::clone(&array);
+            thread::spawn(move || array.iter().sum::<u64>())
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 1536);
+    }
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "mock")]
+
+use unswap::mock::{self, Call, MockImpl};
+use unswap::{AllocOptions, UnswapVec};
+
+type MockVec<T> = UnswapVec<T, MockImpl>;
+
+/// Returns the whole region holding the elements of `vec`,
+/// including the part past its length
+fn region(vec: &MockVec<u8>) -> Vec<u8> {
+    mock::contents(vec.as_ptr() as *const _).unwrap()
+}
+
+#[test]
+fn growth_moves_elements() {
+    mock::reset();
+
+    let mut vec = MockVec::new_with(AllocOptions::new());
+    assert_eq!(vec.capacity(), 0);
+    for i in 0..0x1800u32 {
+        vec.push(i);
+    }
+    assert_eq!(vec.len(), 0x1800);
+    assert!(vec.capacity() >= 0x1800);
+    assert!(vec.iter().enumerate().all(|(i, &x)| x == i as u32));
+    assert_eq!(mock::region_count(), 1);
+
+    // Each growth allocates the new region first, then wipes and
+    // releases the old one
+    let calls = mock::calls();
+    let mut current = match calls[0] {
+        Call::Alloc { at: Some(at), .. } => at,
+        ref call => panic!("unexpected call: {:?}", call),
+    };
+    assert!(calls.len() > 1);
+    for pair in calls[1..].chunks(2) {
+        match *pair {
+            [Call::Alloc { at: Some(new), .. }, Call::Free { at, wiped, .. }] => {
+                assert_eq!(at, current);
+                assert!(wiped);
+                current = new;
+            }
+            _ => panic!("unexpected calls: {:?}", pair),
+        }
+    }
+    assert_eq!(current, vec.as_ptr() as usize);
+}
+
+#[test]
+fn failed_push_returns_value() {
+    mock::reset();
+
+    let mut vec = MockVec::try_with_capacity_with(AllocOptions::new(), 0x1000).unwrap();
+    vec.extend_from_slice(&[0xAAu8; 0x1000]);
+    let at = vec.as_ptr();
+
+    mock::fail_mmap_on(1);
+    let (value, error) = vec.try_push(0x55).err().unwrap();
+    assert_eq!(value, 0x55);
+    assert!(!error.is_lock_limit());
+
+    // The vector is left untouched
+    assert_eq!(vec.as_ptr(), at);
+    assert_eq!(vec.len(), 0x1000);
+    assert!(vec.iter().all(|&x| x == 0xAA));
+
+    mock::set_memlock_limit(Some(0x1000));
+    let (value, error) = vec.try_push(0x66).err().unwrap();
+    assert_eq!(value, 0x66);
+    assert!(error.is_lock_limit());
+    assert_eq!(mock::region_count(), 1);
+}
+
+#[test]
+fn removed_elements_are_wiped() {
+    mock::reset();
+
+    let mut vec = MockVec::new_with(AllocOptions::new());
+    vec.extend_from_slice(b"password hunter2");
+    let capacity = vec.capacity();
+
+    assert_eq!(vec.pop(), Some(b'2'));
+    assert_eq!(&region(&vec)[..16], b"password hunter\0");
+
+    vec.truncate(9);
+    assert_eq!(&vec[..], b"password ");
+    assert!(region(&vec)[9..].iter().all(|&x| x == 0));
+
+    vec.clear();
+    assert!(region(&vec).iter().all(|&x| x == 0));
+    assert_eq!(vec.capacity(), capacity);
+}